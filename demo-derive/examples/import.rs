@@ -0,0 +1,10 @@
+use demo_derive::sim_model;
+
+sim_model!(pub Bass = "examples/models/bass.xmile");
+
+fn main() {
+    let mut bass = Bass::new();
+    let series = bass.run(0.0, 10.0, 0.25).unwrap();
+    series.write_csv(std::io::stdout().lock()).unwrap();
+    eprintln!("{}", Bass::XMILE);
+}
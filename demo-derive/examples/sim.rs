@@ -1,7 +1,15 @@
 use demo_derive::{DemoDerive, Fsm};
 use std::collections::HashMap;
 
-fn main() {}
+fn main() {
+    let mut bass = Bass::from_input(&BassInput {
+        total_population: 10_000.0,
+    });
+    let series = bass.run(0.0, 10.0, 0.5).unwrap();
+    series.write_csv(std::io::stdout().lock()).unwrap();
+    let BassOutput { clients, .. } = bass.output();
+    eprintln!("clients after {} steps: {clients:.2}", series.len() - 1);
+}
 
 #[derive(DemoDerive)]
 #[demo(go, name = "Peoples", method = "agents")]
@@ -110,6 +118,7 @@ pub struct Population {
 
 #[derive(DemoDerive, Debug, Clone)]
 #[demo(input(name = "Id", ty = "struct"))]
+#[allow(dead_code)]
 pub struct Id(String);
 
 #[derive(DemoDerive, Debug, Clone)]
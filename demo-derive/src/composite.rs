@@ -0,0 +1,82 @@
+use derive_attr_parser::{reflect_assert, Container, Field, Val};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+
+use crate::sim::key_span;
+
+// method = "composited": the fields with `model` hold other DemoDerive models, checked at
+// compile time against their AttrReflect::META.
+// #[demo(model(input = "BassInput", output = "BassOutput"))]     the names the model declares
+// #[demo(output(from = "clients", to = "clients", ty = "f64"))]  `from` is an output of the model
+// #[demo(mapping(from = "population.total", to = "total_population"))]
+//                                     `population` is a sibling model with the output `total`,
+//                                     `total_population` is an input of this model
+pub(crate) fn composite_checks(cont: &Container, runtime: &syn::Path) -> syn::Result<TokenStream> {
+    let mut checks = vec![];
+    for field in cont.fields_with("model") {
+        let ty = field.ty;
+        let model = field.attr("model")?;
+        let span = key_span(field, "model");
+        for (key, names) in [("input", "input_name"), ("output", "output_name")] {
+            if let Ok(Val::Str(name)) = model.get_path(key) {
+                let msg = format!("`{}` declares no {names} or {key}(name) `{name}`", type_name(ty));
+                let cond = quote!(
+                    meta.attr_has_str(&[#names], #name) || meta.attr_has_str(&[#key, "name"], #name)
+                );
+                checks.push(reflect_assert(runtime, ty, cond, &msg, span));
+            }
+        }
+        if let Ok(outputs) = field.attr("output") {
+            let span = key_span(field, "output");
+            for output in outputs.as_slice() {
+                if let Ok(Val::Str(from)) = output.get_path("from") {
+                    checks.push(has_output(runtime, ty, from, span));
+                }
+            }
+        }
+        if let Ok(mappings) = field.attr("mapping") {
+            let span = key_span(field, "mapping");
+            for mapping in mappings.as_slice() {
+                checks.push(mapping_checks(cont, runtime, field, mapping, span)?);
+            }
+        }
+    }
+    Ok(quote!(#(#checks)*))
+}
+
+fn mapping_checks(
+    cont: &Container,
+    runtime: &syn::Path,
+    field: &Field,
+    mapping: &Val,
+    span: Span,
+) -> syn::Result<TokenStream> {
+    let from = mapping.get_path("from")?.as_str()?;
+    let to = mapping.get_path("to")?.as_str()?;
+    let (sibling, output) = from.split_once('.').ok_or_else(|| {
+        let msg = format!("expect mapping(from = \"<field>.<output>\"), got {from:?}");
+        syn::Error::new(span, msg)
+    })?;
+    let sibling = cont
+        .field(sibling)
+        .map_err(|err| syn::Error::new(span, err))?;
+    if !sibling.attrs.contains_key("model") {
+        let msg = format!("mapping from `{}`, which is not a model", sibling.name());
+        return Err(syn::Error::new(span, msg));
+    }
+    let from_check = has_output(runtime, sibling.ty, output, span);
+    let msg = format!("`{}` has no input(from = {to:?})", type_name(field.ty));
+    let cond = quote!(meta.any_field_has_str(&["input", "from"], #to));
+    let to_check = reflect_assert(runtime, field.ty, cond, &msg, span);
+    Ok(quote!(#from_check #to_check))
+}
+
+fn has_output(runtime: &syn::Path, ty: &syn::Type, output: &str, span: Span) -> TokenStream {
+    let msg = format!("`{}` has no output(to = {output:?})", type_name(ty));
+    let cond = quote!(meta.any_field_has_str(&["output", "to"], #output));
+    reflect_assert(runtime, ty, cond, &msg, span)
+}
+
+fn type_name(ty: &syn::Type) -> String {
+    quote!(#ty).to_string()
+}
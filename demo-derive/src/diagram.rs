@@ -0,0 +1,254 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use derive_attr_parser::{Container, Val};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::ext::IdentExt;
+
+use crate::graph::free_idents;
+use crate::sim::{Model, Node};
+
+// `Bass::DIAGRAM`, the stock-and-flow diagram of the model: the stocks as boxes, a flow as
+// valve on the edges from its `from` stock to its `to` stock, clouds for missing ends, the vars
+// and params as the sources of dashed arrows to the nodes whose `val` uses them.
+// #[demo(diagram = "mermaid")]                      DOT without it, or with "dot"
+// #[demo(diagram(format = "mermaid", write))]      also writes OUT_DIR/Bass.mmd (Bass.dot), the
+//                                                  crate needs a build.rs for cargo to set OUT_DIR
+// DOT ids are quoted, `edge` or `node` are keywords unquoted. Mermaid ids are the names behind
+// `n_`, the label the name, as `end` is a keyword. Cloud ids have a `.` in DOT and a `c_` in
+// Mermaid, no field name gives them.
+
+const DIAGRAM: &str = "diagram";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Dot,
+    Mermaid,
+}
+
+/// `const DIAGRAM: &str` of the model, written to OUT_DIR on request.
+pub(crate) fn diagram_tokens(cont: &Container, model: &Model) -> syn::Result<TokenStream> {
+    let span = cont
+        .entries
+        .iter()
+        .find(|entry| entry.key == DIAGRAM)
+        .map(|entry| entry.span)
+        .unwrap_or_else(|| cont.ident.span());
+    let (format, write) = match cont.attrs.get(DIAGRAM) {
+        None | Some(Val::Empty) => (Format::Dot, false),
+        Some(Val::Str(format)) => (parse_format(format, span)?, false),
+        Some(Val::Map(opts)) => {
+            let format = match opts.get("format") {
+                None => Format::Dot,
+                Some(Val::Str(format)) => parse_format(format, span)?,
+                Some(_) => return Err(syn::Error::new(span, "expect format = \"dot\" #diagram")),
+            };
+            (format, opts.contains_key("write"))
+        }
+        Some(_) => return Err(syn::Error::new(span, "expect one diagram = \"dot\" #diagram")),
+    };
+    let name = cont.ident.to_string();
+    let (text, ext) = match format {
+        Format::Dot => (dot(&name, model), "dot"),
+        Format::Mermaid => (mermaid(model), "mmd"),
+    };
+    if write {
+        let dir = std::env::var_os("OUT_DIR").map(PathBuf::from).ok_or_else(|| {
+            syn::Error::new(span, "OUT_DIR is not set, add a build.rs to write the diagram #diagram")
+        })?;
+        let path = dir.join(format!("{name}.{ext}"));
+        // keep the file untouched when nothing changed.
+        if std::fs::read_to_string(&path).ok().as_deref() != Some(text.as_str()) {
+            std::fs::write(&path, &text).map_err(|err| {
+                syn::Error::new(span, format!("cannot write {}: {err} #diagram", path.display()))
+            })?;
+        }
+    }
+    Ok(cont.impl_inherent(quote! {
+        /// The stock-and-flow diagram of the model.
+        pub const DIAGRAM: &'static str = #text;
+    }))
+}
+
+fn parse_format(format: &str, span: Span) -> syn::Result<Format> {
+    match format {
+        "dot" => Ok(Format::Dot),
+        "mermaid" => Ok(Format::Mermaid),
+        _ => {
+            let msg = format!("unknown diagram format `{format}`, expect dot or mermaid #diagram");
+            Err(syn::Error::new(span, msg))
+        }
+    }
+}
+
+// `(dep, node)` for every model node the val of `node` uses.
+fn dependencies(model: &Model) -> Vec<(syn::Ident, &syn::Ident)> {
+    let nodes: Vec<&Node> = model.initials().into_iter().chain(model.computed()).collect();
+    let mut deps = vec![];
+    for node in &nodes {
+        let refs = node.val.as_ref().map(free_idents).unwrap_or_default();
+        for dep in refs {
+            if nodes.iter().any(|known| known.ident == dep) {
+                deps.push((dep, &node.ident));
+            }
+        }
+    }
+    deps
+}
+
+fn dot(name: &str, model: &Model) -> String {
+    let id = |ident: &syn::Ident| format!("\"{}\"", ident.unraw());
+    let mut out = String::new();
+    let _ = writeln!(out, "digraph \"{name}\" {{");
+    out.push_str("  rankdir=LR;\n");
+    for stock in &model.stocks {
+        let _ = writeln!(out, "  {} [shape=box];", id(&stock.ident));
+    }
+    for flow in &model.flows {
+        let valve = &flow.node.ident;
+        let _ = writeln!(out, "  {} [shape=circle];", id(valve));
+        let from = match &flow.from {
+            Some(stock) => id(stock),
+            None => cloud_dot(&mut out, valve, "source"),
+        };
+        let to = match &flow.to {
+            Some(stock) => id(stock),
+            None => cloud_dot(&mut out, valve, "sink"),
+        };
+        let _ = writeln!(out, "  {from} -> {} [style=bold, arrowhead=none];", id(valve));
+        let _ = writeln!(out, "  {} -> {to} [style=bold];", id(valve));
+    }
+    for var in &model.vars {
+        let _ = writeln!(out, "  {} [shape=ellipse];", id(&var.ident));
+    }
+    for param in &model.params {
+        let _ = writeln!(out, "  {} [shape=plaintext];", id(&param.ident));
+    }
+    for (dep, node) in dependencies(model) {
+        let _ = writeln!(out, "  {} -> {} [style=dashed];", id(&dep), id(node));
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn cloud_dot(out: &mut String, valve: &syn::Ident, end: &str) -> String {
+    let cloud = format!("\"{}.{end}\"", valve.unraw());
+    let _ = writeln!(out, "  {cloud} [shape=none, label=\"cloud\"];");
+    cloud
+}
+
+fn mermaid(model: &Model) -> String {
+    let id = |ident: &syn::Ident| format!("n_{}", ident.unraw());
+    let mut out = String::from("flowchart LR\n");
+    for stock in &model.stocks {
+        let _ = writeln!(out, "  {}[\"{}\"]", id(&stock.ident), stock.ident.unraw());
+    }
+    for flow in &model.flows {
+        let valve = &flow.node.ident;
+        let _ = writeln!(out, "  {}((\"{}\"))", id(valve), valve.unraw());
+        let from = match &flow.from {
+            Some(stock) => id(stock),
+            None => cloud_mermaid(&mut out, valve, "source"),
+        };
+        let to = match &flow.to {
+            Some(stock) => id(stock),
+            None => cloud_mermaid(&mut out, valve, "sink"),
+        };
+        let _ = writeln!(out, "  {from} === {}", id(valve));
+        let _ = writeln!(out, "  {} ==> {to}", id(valve));
+    }
+    for var in &model.vars {
+        let _ = writeln!(out, "  {}([\"{}\"])", id(&var.ident), var.ident.unraw());
+    }
+    for param in &model.params {
+        let _ = writeln!(out, "  {}>\"{}\"]", id(&param.ident), param.ident.unraw());
+    }
+    for (dep, node) in dependencies(model) {
+        let _ = writeln!(out, "  {} -.-> {}", id(&dep), id(node));
+    }
+    out
+}
+
+fn cloud_mermaid(out: &mut String, valve: &syn::Ident, end: &str) -> String {
+    let cloud = format!("c_{}_{end}", valve.unraw());
+    let _ = writeln!(out, "  {cloud}{{{{\"cloud\"}}}}");
+    cloud
+}
+
+#[cfg(test)]
+mod tests {
+    use derive_attr_parser::{from_ast, Ctxt, Symbol};
+
+    use super::*;
+
+    // keyword names, and `edge_source` where the cloud of `edge` used to be.
+    fn model() -> Model {
+        let input: syn::DeriveInput = syn::parse_quote! {
+            struct Graph {
+                #[demo(param(val = "0.1"))]
+                end: f64,
+                #[demo(stock(val = "1.0"))]
+                node: f64,
+                #[demo(stock(val = "0.0"))]
+                edge_source: f64,
+                #[demo(flow(to = "node", val = "end"))]
+                edge: f64,
+                #[demo(flow(from = "node", to = "edge_source", val = "graph"))]
+                r#type: f64,
+                #[demo(var(val = "node * end"))]
+                graph: f64,
+            }
+        };
+        let cx = Ctxt::new();
+        let cont = from_ast(&cx, &input, Symbol("demo"));
+        cx.check().unwrap();
+        Model::from_container(&cont.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn dot_ids_are_quoted() {
+        let expected = r#"digraph "Graph" {
+  rankdir=LR;
+  "node" [shape=box];
+  "edge_source" [shape=box];
+  "edge" [shape=circle];
+  "edge.source" [shape=none, label="cloud"];
+  "edge.source" -> "edge" [style=bold, arrowhead=none];
+  "edge" -> "node" [style=bold];
+  "type" [shape=circle];
+  "node" -> "type" [style=bold, arrowhead=none];
+  "type" -> "edge_source" [style=bold];
+  "graph" [shape=ellipse];
+  "end" [shape=plaintext];
+  "node" -> "graph" [style=dashed];
+  "end" -> "graph" [style=dashed];
+  "end" -> "edge" [style=dashed];
+  "graph" -> "type" [style=dashed];
+}
+"#;
+        assert_eq!(dot("Graph", &model()), expected);
+    }
+
+    #[test]
+    fn mermaid_ids_are_prefixed() {
+        let expected = r#"flowchart LR
+  n_node["node"]
+  n_edge_source["edge_source"]
+  n_edge(("edge"))
+  c_edge_source{{"cloud"}}
+  c_edge_source === n_edge
+  n_edge ==> n_node
+  n_type(("type"))
+  n_node === n_type
+  n_type ==> n_edge_source
+  n_graph(["graph"])
+  n_end>"end"]
+  n_node -.-> n_graph
+  n_end -.-> n_graph
+  n_end -.-> n_edge
+  n_graph -.-> n_type
+"#;
+        assert_eq!(mermaid(&model()), expected);
+    }
+}
@@ -0,0 +1,219 @@
+use syn::visit::Visit;
+
+use crate::sim::Node;
+
+// The vals of a system_dynamics model refer to other fields by name:
+// #[demo(var(val = "clients * contact_rate * sales_fraction * potential_clients / total_population"))]
+// depends on the stocks clients and potential_clients and on the params, and is evaluated after
+// every var or flow it uses. A val using itself, directly or through others, is an algebraic loop.
+// Single idents are field names, except `self` and idents starting uppercase (consts, unit
+// structs). Paths like `f64::consts::PI` and the function of a call like `f(x)` are no names.
+
+/// The names `expr` refers to, in order of appearance.
+pub(crate) fn free_idents(expr: &syn::Expr) -> Vec<syn::Ident> {
+    let mut refs = Refs(vec![]);
+    refs.visit_expr(expr);
+    refs.0
+}
+
+struct Refs(Vec<syn::Ident>);
+
+impl<'ast> Visit<'ast> for Refs {
+    fn visit_expr_path(&mut self, expr: &'ast syn::ExprPath) {
+        let ident = match (&expr.qself, expr.path.get_ident()) {
+            (None, Some(ident)) => ident,
+            _ => return,
+        };
+        let name = ident.to_string();
+        if name != "self" && !name.starts_with(char::is_uppercase) && !self.0.contains(ident) {
+            self.0.push(ident.clone());
+        }
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        if !matches!(&*call.func, syn::Expr::Path(_)) {
+            self.visit_expr(&call.func);
+        }
+        for arg in &call.args {
+            self.visit_expr(arg);
+        }
+    }
+}
+
+/// The order to evaluate `nodes` in, every node after the nodes its val uses.
+///
+/// `sources` are given before any of `nodes`, the other `fields` of the model cannot be used.
+pub(crate) fn eval_order(
+    nodes: &[&Node],
+    sources: &[&syn::Ident],
+    fields: &[&syn::Ident],
+) -> syn::Result<Vec<usize>> {
+    let mut errors: Option<syn::Error> = None;
+    let mut push = |err: syn::Error| match &mut errors {
+        Some(errors) => errors.combine(err),
+        None => errors = Some(err),
+    };
+    let mut deps = vec![];
+    for node in nodes {
+        let mut edges = vec![];
+        let refs = node.val.as_ref().map(free_idents).unwrap_or_default();
+        for name in refs {
+            let ident = &node.ident;
+            if let Some(at) = nodes.iter().position(|node| node.ident == name) {
+                edges.push(at);
+            } else if sources.contains(&&name) {
+                continue;
+            } else if fields.contains(&&name) {
+                let msg = format!("`{name}` cannot be used in the val of `{ident}` #eval_order");
+                push(syn::Error::new(node.val_span, msg));
+            } else {
+                let msg = format!("unknown field `{name}` in the val of `{ident}` #eval_order");
+                push(syn::Error::new(node.val_span, msg));
+            }
+        }
+        deps.push(edges);
+    }
+    if let Some(errors) = errors {
+        return Err(errors);
+    }
+
+    let mut order = Vec::with_capacity(nodes.len());
+    let mut state = vec![Mark::New; nodes.len()];
+    let mut path = vec![];
+    for at in 0..nodes.len() {
+        visit(at, &deps, &mut state, &mut path, &mut order)
+            .map_err(|cycle| loop_error(nodes, &cycle))?;
+    }
+    Ok(order)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mark {
+    New,
+    OnPath,
+    Done,
+}
+
+// Depth first, `order` gets a node after its deps, a dep on the `path` closes a loop.
+fn visit(
+    at: usize,
+    deps: &[Vec<usize>],
+    state: &mut [Mark],
+    path: &mut Vec<usize>,
+    order: &mut Vec<usize>,
+) -> Result<(), Vec<usize>> {
+    match state[at] {
+        Mark::Done => return Ok(()),
+        Mark::OnPath => {
+            let from = path.iter().position(|node| *node == at).unwrap_or_default();
+            return Err(path[from..].to_vec());
+        }
+        Mark::New => {}
+    }
+    state[at] = Mark::OnPath;
+    path.push(at);
+    for dep in &deps[at] {
+        visit(*dep, deps, state, path, order)?;
+    }
+    path.pop();
+    state[at] = Mark::Done;
+    order.push(at);
+    Ok(())
+}
+
+// One error at the val of every node in the loop.
+fn loop_error(nodes: &[&Node], cycle: &[usize]) -> syn::Error {
+    let names: Vec<_> = cycle
+        .iter()
+        .chain(cycle.first())
+        .map(|at| format!("`{}`", nodes[*at].ident))
+        .collect();
+    let msg = format!("algebraic loop {} #eval_order", names.join(" -> "));
+    let mut errors = cycle.iter().map(|at| syn::Error::new(nodes[*at].val_span, &msg));
+    let mut error = errors.next().expect("a loop has a node");
+    error.extend(errors);
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use proc_macro2::Span;
+
+    use super::*;
+
+    fn node(ident: &str, val: &str) -> Node {
+        Node {
+            ident: syn::Ident::new(ident, Span::call_site()),
+            val: Some(syn::parse_str(val).unwrap()),
+            span: Span::call_site(),
+            val_span: Span::call_site(),
+        }
+    }
+
+    fn order(nodes: &[Node], sources: &[&str]) -> syn::Result<Vec<String>> {
+        let nodes: Vec<&Node> = nodes.iter().collect();
+        let sources: Vec<_> =
+            sources.iter().map(|s| syn::Ident::new(s, Span::call_site())).collect();
+        let sources: Vec<_> = sources.iter().collect();
+        let order = eval_order(&nodes, &sources, &[])?;
+        Ok(order.into_iter().map(|at| nodes[at].ident.to_string()).collect())
+    }
+
+    fn position(order: &[String], ident: &str) -> usize {
+        order.iter().position(|name| name == ident).unwrap()
+    }
+
+    #[test]
+    fn free_idents_skip_paths_calls_and_consts() {
+        let expr = syn::parse_str("f(a) + b.max(a) * PI + f64::consts::E - self.c + (d)").unwrap();
+        let names: Vec<_> = free_idents(&expr).iter().map(ToString::to_string).collect();
+        assert_eq!(names, ["a", "b", "d"]);
+    }
+
+    #[test]
+    fn diamond() {
+        // declared in reverse, top needs left and right, both need base.
+        let nodes = [
+            node("top", "left + right"),
+            node("right", "base * 2.0"),
+            node("left", "base + stock"),
+            node("base", "stock"),
+        ];
+        let order = order(&nodes, &["stock"]).unwrap();
+        assert_eq!(order.len(), 4);
+        assert_eq!(order[0], "base");
+        assert!(position(&order, "left") < position(&order, "top"));
+        assert!(position(&order, "right") < position(&order, "top"));
+    }
+
+    #[test]
+    fn self_loop() {
+        let nodes = [node("a", "stock"), node("x", "x + a")];
+        let err = order(&nodes, &["stock"]).unwrap_err();
+        assert_eq!(err.to_string(), "algebraic loop `x` -> `x` #eval_order");
+    }
+
+    #[test]
+    fn two_node_loop() {
+        let nodes = [node("a", "b * 2.0"), node("b", "a + 1.0"), node("c", "a")];
+        let err = order(&nodes, &[]).unwrap_err();
+        // one error at each node of the loop.
+        let msgs: Vec<_> = err.into_iter().map(|err| err.to_string()).collect();
+        assert_eq!(msgs, ["algebraic loop `a` -> `b` -> `a` #eval_order"; 2]);
+    }
+
+    #[test]
+    fn unknown_and_unusable_names() {
+        let nodes = [node("a", "missing + other")];
+        let other = syn::Ident::new("other", Span::call_site());
+        let err = eval_order(&[&nodes[0]], &[], &[&other]).unwrap_err();
+        let msgs: Vec<_> = err.into_iter().map(|err| err.to_string()).collect();
+        assert_eq!(
+            msgs,
+            [
+                "unknown field `missing` in the val of `a` #eval_order",
+                "`other` cannot be used in the val of `a` #eval_order",
+            ]
+        );
+    }
+}
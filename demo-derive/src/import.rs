@@ -0,0 +1,650 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote_spanned};
+use syn::parse::{Parse, ParseStream};
+use syn::Token;
+
+use crate::xml::{self, Element};
+
+// sim_model!("models/bass.xmile") reads an XMILE file, relative to CARGO_MANIFEST_DIR, into a
+// struct deriving DemoDerive, so it goes through the same parsing as a model written by hand:
+// <stock>                  #[demo(stock(val = "<eqn>"))], its inflows and outflows give the
+//                          `from` and `to` of the flows
+// <flow>                   #[demo(flow(from = "..", to = "..", val = "<eqn>"))]
+// <aux> with a number      #[demo(param(val = "<eqn>"))]
+// <aux>                    #[demo(var(val = "<eqn>"))]
+// <sim_specs method=..>    ode_solver and sim_specs(start, stop, dt)
+// Names become snake_case fields, `Potential Clients` is potential_clients. The struct is named
+// after the header name, sim_model!(pub Bass = "models/bass.xmile") names it explicitly.
+
+/// `[vis] [Ident =] "path"`
+pub(crate) struct SimModel {
+    vis: syn::Visibility,
+    ident: Option<syn::Ident>,
+    path: syn::LitStr,
+}
+
+impl Parse for SimModel {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let vis = input.parse()?;
+        let ident = if input.peek(syn::Ident) {
+            let ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Some(ident)
+        } else {
+            None
+        };
+        let path = input.parse()?;
+        Ok(SimModel { vis, ident, path })
+    }
+}
+
+pub(crate) fn sim_model_expand(input: SimModel) -> syn::Result<TokenStream> {
+    let span = input.path.span();
+    let path = std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join(input.path.value());
+    let error = |msg: String| syn::Error::new(span, format!("{msg}, {} #sim_model", path.display()));
+    let text = std::fs::read_to_string(&path).map_err(|err| error(format!("cannot read: {err}")))?;
+    let root = xml::parse(&text).map_err(|msg| error(format!("invalid xml: {msg}")))?;
+    let model = Import::from_xmile(&root).map_err(error)?;
+
+    let vis = &input.vis;
+    let ident = match input.ident {
+        Some(ident) => ident,
+        None => {
+            let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned());
+            let name = model.name.clone().or(stem).unwrap_or_default();
+            let mut ident: syn::Ident = syn::parse_str(&camel(&name))
+                .map_err(|_| error(format!("no struct name in `{name}`")))?;
+            ident.set_span(span);
+            ident
+        }
+    };
+    // all at the path literal, the derive reports a model it rejects there.
+    let lit = |val: &str| syn::LitStr::new(val, span);
+    let name = lit(&model.name.unwrap_or_else(|| ident.to_string()));
+    let solver = lit(model.solver);
+    let [start, stop, dt] = model.specs.each_ref().map(|spec| lit(spec));
+    let fields = model.fields.iter().map(|field| {
+        let ident = format_ident!("{}", field.ident, span = span);
+        let role = format_ident!("{}", field.role, span = span);
+        let val = lit(&field.val);
+        let ends = field.ends.iter().map(|(end, stock)| {
+            let end = format_ident!("{}", end, span = span);
+            let stock = lit(stock);
+            quote_spanned!(span=> #end = #stock,)
+        });
+        let doc = field.doc.iter().map(|doc| lit(doc));
+        quote_spanned! {span=>
+            #(#[doc = #doc])*
+            #[demo(#role(#(#ends)* val = #val))]
+            #vis #ident: f64,
+        }
+    });
+    let abs = lit(&path.display().to_string());
+    Ok(quote_spanned! {span=>
+        #[derive(::demo_derive::DemoDerive, Debug, Clone)]
+        #[demo(
+            name = #name,
+            method = "system_dynamics",
+            ode_solver = #solver,
+            sim_specs(start = #start, stop = #stop, dt = #dt)
+        )]
+        #vis struct #ident {
+            #(#fields)*
+        }
+        const _: &[u8] = include_bytes!(#abs);
+    })
+}
+
+/// A model read from XMILE.
+struct Import {
+    name: Option<String>,
+    solver: &'static str,
+    /// start, stop and dt.
+    specs: [String; 3],
+    fields: Vec<ImportField>,
+}
+
+struct ImportField {
+    ident: String,
+    role: &'static str,
+    val: String,
+    /// `("from", stock)` and `("to", stock)` of a flow.
+    ends: Vec<(&'static str, String)>,
+    doc: Option<String>,
+}
+
+impl Import {
+    fn from_xmile(root: &Element) -> Result<Import, String> {
+        if root.name != "xmile" {
+            return Err(format!("expect <xmile>, found <{}>", root.name));
+        }
+        let name = root
+            .child("header")
+            .and_then(|header| header.child_text("name"))
+            .filter(|name| !name.is_empty())
+            .map(str::to_string);
+        let specs = root.child("sim_specs");
+        let solver = match specs.and_then(|specs| specs.attr("method")) {
+            None => "euler",
+            Some(method) => match method.to_lowercase().as_str() {
+                "euler" => "euler",
+                "rk2" => "heun",
+                "rk4" => "rk4",
+                "rk45" => "rk45",
+                _ => return Err(format!("sim_specs method `{method}` is not supported")),
+            },
+        };
+        let spec = |key: &str, default: &str| -> Result<String, String> {
+            let Some(spec) = specs.and_then(|specs| specs.child(key)) else {
+                return Ok(default.to_string());
+            };
+            let val: f64 = spec
+                .text
+                .trim()
+                .parse()
+                .map_err(|_| format!("sim_specs <{key}> `{}` is not a number", spec.text.trim()))?;
+            let val = if spec.attr("reciprocal") == Some("true") { 1.0 / val } else { val };
+            Ok(val.to_string())
+        };
+        let specs = [spec("start", "0")?, spec("stop", "100")?, spec("dt", "1")?];
+
+        let mut models = root.children("model");
+        let model = models.next().ok_or("no <model>")?;
+        if models.next().is_some() {
+            return Err("several <model>, modules are not supported".to_string());
+        }
+        let variables = model.child("variables").ok_or("no <variables> in <model>")?;
+
+        // the stocks the flows drain and fill.
+        let mut ends: HashMap<String, Vec<(&'static str, String)>> = HashMap::new();
+        for stock in variables.children("stock") {
+            let ident = snake(stock.attr("name").unwrap_or_default());
+            for (tag, end) in [("outflow", "from"), ("inflow", "to")] {
+                for flow in stock.children(tag) {
+                    let flow = snake(&flow.text);
+                    let flow_ends = ends.entry(flow.clone()).or_default();
+                    if flow_ends.iter().any(|(known, _)| *known == end) {
+                        return Err(format!("flow `{flow}` is an {tag} of two stocks"));
+                    }
+                    flow_ends.push((end, ident.clone()));
+                }
+            }
+        }
+
+        let mut fields = vec![];
+        for var in &variables.children {
+            let name = var.attr("name").ok_or(format!("<{}> without name", var.name))?;
+            let ident = snake(name);
+            if ident.is_empty() {
+                return Err(format!("no field name in `{name}`"));
+            }
+            let eqn = var.child_text("eqn").unwrap_or("0");
+            let val = equation(eqn).map_err(|msg| format!("{msg} in the eqn of `{name}`"))?;
+            let role = match var.name.as_str() {
+                "stock" => "stock",
+                "flow" => "flow",
+                "aux" if var.child("gf").is_some() => {
+                    return Err(format!("`{name}` is a graphical function, they are not supported"))
+                }
+                "aux" if eqn.parse::<f64>().is_ok() => "param",
+                "aux" => "var",
+                other => return Err(format!("<{other}> `{name}` is not supported")),
+            };
+            let ends = match role {
+                "flow" => ends.remove(&ident).unwrap_or_default(),
+                _ => vec![],
+            };
+            let doc = var.child_text("doc").filter(|doc| !doc.is_empty()).map(str::to_string);
+            fields.push(ImportField { ident, role, val, ends, doc });
+        }
+        if let Some(flow) = ends.keys().next() {
+            return Err(format!("no <flow> `{flow}` for the inflow or outflow of a stock"));
+        }
+        Ok(Import { name, solver, specs, fields })
+    }
+}
+
+/// `Potential Clients` as `potential_clients`.
+fn snake(name: &str) -> String {
+    let mut out = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() {
+            out.extend(c.to_lowercase());
+        } else if !out.is_empty() && !out.ends_with('_') {
+            out.push('_');
+        }
+    }
+    let mut out = out.trim_end_matches('_').to_string();
+    if out.starts_with(|c: char| c.is_ascii_digit()) {
+        out.insert(0, '_');
+    }
+    if syn::parse_str::<syn::Ident>(&out).is_err() && !out.is_empty() {
+        out.push('_');
+    }
+    out
+}
+
+/// `bass diffusion` as `BassDiffusion`.
+fn camel(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            let first = chars.next().map(|c| c.to_uppercase().to_string()).unwrap_or_default();
+            first + chars.as_str()
+        })
+        .collect()
+}
+
+// XMILE equations, parsed by precedence into `Eqn` and written back as Rust:
+// a ^ b           a.powf(b)
+// MAX(a, b)       a.max(b), the same for MIN, and ABS(a) as a.abs() for ABS EXP LN LOG10 SQRT
+//                 SIN COS TAN, INT(a) as a.floor()
+// a MOD b         a % b
+// IF c THEN a ELSE b, comparisons, AND, OR, NOT  as `if` and bool, 1.0 or 0.0 as number
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(String),
+    Name(String),
+    Op(&'static str),
+    Open,
+    Close,
+    Comma,
+}
+
+#[derive(Debug)]
+enum Eqn {
+    Num(String),
+    Name(String),
+    Neg(Box<Eqn>),
+    Not(Box<Eqn>),
+    Bin(&'static str, Box<Eqn>, Box<Eqn>),
+    Call(String, Vec<Eqn>),
+    If(Box<Eqn>, Box<Eqn>, Box<Eqn>),
+}
+
+/// The XMILE equation `eqn` as Rust expression.
+fn equation(eqn: &str) -> Result<String, String> {
+    let tokens = tokenize(eqn)?;
+    let mut parser = EqnParser { tokens, at: 0 };
+    let parsed = parser.expr(0)?;
+    if let Some(token) = parser.tokens.get(parser.at) {
+        return Err(format!("unexpected {token:?}"));
+    }
+    Ok(number(&parsed)?.0)
+}
+
+fn tokenize(eqn: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let chars: Vec<char> = eqn.chars().collect();
+    let mut at = 0;
+    let take = |at: &mut usize, f: &dyn Fn(char) -> bool| {
+        let start = *at;
+        while *at < chars.len() && f(chars[*at]) {
+            *at += 1;
+        }
+        chars[start..*at].iter().collect::<String>()
+    };
+    while at < chars.len() {
+        let c = chars[at];
+        let next = chars.get(at + 1).copied();
+        if c.is_whitespace() {
+            at += 1;
+        } else if c.is_ascii_digit() || c == '.' && next.is_some_and(|n| n.is_ascii_digit()) {
+            let mut num = take(&mut at, &|c| c.is_ascii_digit() || c == '.');
+            if matches!(chars.get(at), Some('e' | 'E')) {
+                at += 1;
+                let sign = take(&mut at, &|c| c == '+' || c == '-');
+                num = format!("{num}e{sign}{}", take(&mut at, &|c| c.is_ascii_digit()));
+            }
+            tokens.push(Token::Num(num));
+        } else if c.is_alphabetic() || c == '_' {
+            let name = take(&mut at, &|c| c.is_alphanumeric() || c == '_' || c == '$');
+            tokens.push(match name.to_uppercase().as_str() {
+                "AND" => Token::Op("AND"),
+                "OR" => Token::Op("OR"),
+                "NOT" => Token::Op("NOT"),
+                "MOD" => Token::Op("MOD"),
+                _ => Token::Name(name),
+            });
+        } else if c == '"' {
+            at += 1;
+            let name = take(&mut at, &|c| c != '"');
+            at += 1;
+            tokens.push(Token::Name(name));
+        } else {
+            let (token, len) = match (c, next) {
+                ('<', Some('=')) => (Token::Op("<="), 2),
+                ('>', Some('=')) => (Token::Op(">="), 2),
+                ('<', Some('>')) => (Token::Op("<>"), 2),
+                ('<', _) => (Token::Op("<"), 1),
+                ('>', _) => (Token::Op(">"), 1),
+                ('=', _) => (Token::Op("="), 1),
+                ('+', _) => (Token::Op("+"), 1),
+                ('-', _) => (Token::Op("-"), 1),
+                ('*', _) => (Token::Op("*"), 1),
+                ('/', _) => (Token::Op("/"), 1),
+                ('^', _) => (Token::Op("^"), 1),
+                ('(', _) => (Token::Open, 1),
+                (')', _) => (Token::Close, 1),
+                (',', _) => (Token::Comma, 1),
+                _ => return Err(format!("unexpected `{c}`")),
+            };
+            tokens.push(token);
+            at += len;
+        }
+    }
+    Ok(tokens)
+}
+
+// The precedence of binary operators, `^` binds right.
+fn precedence(op: &str) -> Option<u8> {
+    match op {
+        "OR" => Some(1),
+        "AND" => Some(2),
+        "=" | "<>" | "<" | "<=" | ">" | ">=" => Some(3),
+        "+" | "-" => Some(4),
+        "*" | "/" | "MOD" => Some(5),
+        "^" => Some(7),
+        _ => None,
+    }
+}
+
+struct EqnParser {
+    tokens: Vec<Token>,
+    at: usize,
+}
+
+impl EqnParser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.at).cloned();
+        self.at += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Name(name)) if name.eq_ignore_ascii_case(keyword) => Ok(()),
+            _ => Err(format!("expect {keyword}")),
+        }
+    }
+
+    fn expr(&mut self, min: u8) -> Result<Eqn, String> {
+        let mut lhs = self.unary()?;
+        while let Some(Token::Op(op)) = self.tokens.get(self.at) {
+            let Some(prec) = precedence(op).filter(|prec| *prec >= min) else {
+                break;
+            };
+            let op = *op;
+            self.at += 1;
+            let rhs = self.expr(if op == "^" { prec } else { prec + 1 })?;
+            lhs = Eqn::Bin(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    // `-a ^ b` is `-(a ^ b)`.
+    fn unary(&mut self) -> Result<Eqn, String> {
+        match self.tokens.get(self.at) {
+            Some(Token::Op("-")) => {
+                self.at += 1;
+                Ok(Eqn::Neg(Box::new(self.expr(6)?)))
+            }
+            Some(Token::Op("+")) => {
+                self.at += 1;
+                self.expr(6)
+            }
+            Some(Token::Op("NOT")) => {
+                self.at += 1;
+                Ok(Eqn::Not(Box::new(self.expr(6)?)))
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Eqn, String> {
+        match self.next() {
+            Some(Token::Num(num)) => Ok(Eqn::Num(num)),
+            Some(Token::Name(name)) if name.eq_ignore_ascii_case("IF") => {
+                let cond = self.expr(0)?;
+                self.keyword("THEN")?;
+                let then = self.expr(0)?;
+                self.keyword("ELSE")?;
+                let other = self.expr(0)?;
+                Ok(Eqn::If(Box::new(cond), Box::new(then), Box::new(other)))
+            }
+            Some(Token::Name(name)) if self.tokens.get(self.at) == Some(&Token::Open) => {
+                self.at += 1;
+                let mut args = vec![];
+                if self.tokens.get(self.at) == Some(&Token::Close) {
+                    self.at += 1;
+                    return Ok(Eqn::Call(name, args));
+                }
+                loop {
+                    args.push(self.expr(0)?);
+                    match self.next() {
+                        Some(Token::Comma) => continue,
+                        Some(Token::Close) => return Ok(Eqn::Call(name, args)),
+                        _ => return Err(format!("expect `,` or `)` in {name}(..)")),
+                    }
+                }
+            }
+            Some(Token::Name(name)) => Ok(Eqn::Name(name)),
+            Some(Token::Open) => {
+                let inner = self.expr(0)?;
+                match self.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => Err("expect `)`".to_string()),
+                }
+            }
+            Some(token) => Err(format!("unexpected {token:?}")),
+            None => Err("unexpected end".to_string()),
+        }
+    }
+}
+
+// Rust precedence of the written expression: `if` 0, || 1, && 2, comparison 3, + - 4,
+// * / % 5, unary 6, method calls and atoms 8.
+type Rust = (String, u8);
+
+fn wrap((rust, prec): Rust, min: u8) -> String {
+    if prec < min {
+        format!("({rust})")
+    } else {
+        rust
+    }
+}
+
+fn is_bool(eqn: &Eqn) -> bool {
+    match eqn {
+        Eqn::Not(_) => true,
+        Eqn::Bin(op, ..) => precedence(op).is_some_and(|prec| prec <= 3),
+        _ => false,
+    }
+}
+
+// `eqn` as f64 expression.
+fn number(eqn: &Eqn) -> Result<Rust, String> {
+    if is_bool(eqn) {
+        return Ok((format!("if {} {{ 1.0 }} else {{ 0.0 }}", condition(eqn)?.0), 0));
+    }
+    Ok(match eqn {
+        Eqn::Num(num) => (float(num), 8),
+        Eqn::Name(name) if name == "PI" => ("::std::f64::consts::PI".to_string(), 8),
+        Eqn::Name(name) => (snake(name), 8),
+        Eqn::Neg(eqn) => (format!("-{}", wrap(number(eqn)?, 6)), 6),
+        Eqn::Bin("^", base, exp) => {
+            (format!("{}.powf({})", receiver(base)?, number(exp)?.0), 8)
+        }
+        Eqn::Bin(op, lhs, rhs) => {
+            let prec = precedence(op).unwrap_or_default();
+            let op = if *op == "MOD" { "%" } else { op };
+            let (lhs, rhs) = (wrap(number(lhs)?, prec), wrap(number(rhs)?, prec + 1));
+            (format!("{lhs} {op} {rhs}"), prec)
+        }
+        Eqn::Call(name, args) => call(name, args)?,
+        Eqn::If(cond, then, other) => (
+            format!(
+                "if {} {{ {} }} else {{ {} }}",
+                condition(cond)?.0,
+                number(then)?.0,
+                number(other)?.0
+            ),
+            0,
+        ),
+        Eqn::Not(_) => unreachable!("a bool"),
+    })
+}
+
+// `5` as `5.0`, `.5` as `0.5`.
+fn float(num: &str) -> String {
+    let num = if num.starts_with('.') { format!("0{num}") } else { num.to_string() };
+    if num.contains(['.', 'e']) {
+        num
+    } else {
+        format!("{num}.0")
+    }
+}
+
+// The receiver of a method call, a literal needs its type, `2.0.sqrt()` is ambiguous.
+fn receiver(eqn: &Eqn) -> Result<String, String> {
+    match eqn {
+        Eqn::Num(num) => Ok(format!("{}_f64", float(num))),
+        _ => Ok(wrap(number(eqn)?, 8)),
+    }
+}
+
+// `eqn` as bool expression, a number is true unless 0.
+fn condition(eqn: &Eqn) -> Result<Rust, String> {
+    if !is_bool(eqn) {
+        return Ok((format!("{} != 0.0", wrap(number(eqn)?, 4)), 3));
+    }
+    Ok(match eqn {
+        Eqn::Not(eqn) => (format!("!{}", wrap(condition(eqn)?, 6)), 6),
+        Eqn::Bin(op @ ("AND" | "OR"), lhs, rhs) => {
+            let (op, prec) = if *op == "AND" { ("&&", 2) } else { ("||", 1) };
+            let (lhs, rhs) = (wrap(condition(lhs)?, prec), wrap(condition(rhs)?, prec + 1));
+            (format!("{lhs} {op} {rhs}"), prec)
+        }
+        Eqn::Bin(op, lhs, rhs) => {
+            let op = match *op {
+                "=" => "==",
+                "<>" => "!=",
+                op => op,
+            };
+            // comparisons do not chain in Rust, wrap both sides above 3.
+            (format!("{} {op} {}", wrap(number(lhs)?, 4), wrap(number(rhs)?, 4)), 3)
+        }
+        _ => unreachable!("a bool"),
+    })
+}
+
+fn call(name: &str, args: &[Eqn]) -> Result<Rust, String> {
+    let upper = name.to_uppercase();
+    let method = match upper.as_str() {
+        "MAX" | "MIN" if args.len() == 2 => {
+            let receiver = receiver(&args[0])?;
+            let other = number(&args[1])?.0;
+            return Ok((format!("{receiver}.{}({other})", upper.to_lowercase()), 8));
+        }
+        "ABS" | "EXP" | "LN" | "LOG10" | "SQRT" | "SIN" | "COS" | "TAN" => upper.to_lowercase(),
+        "INT" => "floor".to_string(),
+        "PI" if args.is_empty() => return Ok(("::std::f64::consts::PI".to_string(), 8)),
+        _ => return Err(format!("function `{name}` with {} args is not supported", args.len())),
+    };
+    match args {
+        [arg] => Ok((format!("{}.{method}()", receiver(arg)?), 8)),
+        _ => Err(format!("function `{name}` takes one arg")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use derive_attr_parser::{from_ast, Ctxt, Symbol};
+
+    use crate::sim::Model;
+
+    use super::*;
+
+    // `a` and `b` use each other, DemoDerive rejects the cycle.
+    const CYCLE: &str = r#"<xmile version="1.0">
+  <header><name>Cycle</name></header>
+  <model>
+    <variables>
+      <aux name="a"><eqn>b + 1</eqn></aux>
+      <aux name="b"><eqn>a * 2</eqn></aux>
+    </variables>
+  </model>
+</xmile>
+"#;
+
+    #[test]
+    fn derive_errors_point_at_the_path() {
+        let dir = std::env::temp_dir().join(format!("demo-derive-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("cycle.xmile");
+        std::fs::write(&file, CYCLE).unwrap();
+
+        let src = format!("pub Cycle =\n    {:?}", file.display().to_string());
+        let tokens = sim_model_expand(syn::parse_str(&src).unwrap()).unwrap();
+        let file: syn::File = syn::parse2(tokens).unwrap();
+        let syn::Item::Struct(item) = &file.items[0] else { panic!("expect a struct") };
+        let input: syn::DeriveInput = item.clone().into();
+        let cx = Ctxt::new();
+        let cont = from_ast(&cx, &input, Symbol("demo"));
+        cx.check().unwrap();
+        let err = Model::from_container(&cont.unwrap()).err().unwrap();
+        let start = err.span().start();
+        assert_eq!((start.line, start.column), (2, 4));
+    }
+
+    #[test]
+    fn equations() {
+        let cases = [
+            ("-a ^ 2", "-a.powf(2.0)"),
+            ("(-a) ^ 2", "(-a).powf(2.0)"),
+            ("a ^ b ^ c", "a.powf(b.powf(c))"),
+            ("-a * b", "-a * b"),
+            ("2 ^ -x", "2.0_f64.powf(-x)"),
+            ("a - (b - c)", "a - (b - c)"),
+            ("a MOD b", "a % b"),
+            (r#"MAX("Sales From Ad", .5)"#, "sales_from_ad.max(0.5)"),
+            (
+                "IF a > 0 THEN IF b > 0 THEN 1 ELSE 2 ELSE 3",
+                "if a > 0.0 { if b > 0.0 { 1.0 } else { 2.0 } } else { 3.0 }",
+            ),
+            (
+                "1 + IF a THEN b ELSE c",
+                "1.0 + (if a != 0.0 { b } else { c })",
+            ),
+            ("NOT a AND b OR c = 1", "if !(a != 0.0) && b != 0.0 || c == 1.0 { 1.0 } else { 0.0 }"),
+            ("SIN(PI / 6)", "(::std::f64::consts::PI / 6.0).sin()"),
+        ];
+        for (eqn, expected) in cases {
+            assert_eq!(equation(eqn).as_deref(), Ok(expected), "{eqn}");
+        }
+    }
+
+    #[test]
+    fn equation_errors() {
+        let cases = [
+            ("SMTH1(a, 3)", "function `SMTH1` with 2 args is not supported"),
+            ("LOG(a)", "function `LOG` with 1 args is not supported"),
+            ("SQRT(a, b)", "function `SQRT` takes one arg"),
+            ("IF a THEN b", "expect ELSE"),
+            ("(a + b", "expect `)`"),
+            ("a # b", "unexpected `#`"),
+            ("a b", "unexpected Name(\"b\")"),
+        ];
+        for (eqn, msg) in cases {
+            assert_eq!(equation(eqn).unwrap_err(), msg, "{eqn}");
+        }
+    }
+}
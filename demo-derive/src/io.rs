@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+
+use derive_attr_parser::{Container, Val};
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+
+use crate::sim::{init_tokens, key_span, Model};
+
+// The Input and Output structs of a system_dynamics model:
+// #[demo(input_name = "BassInput")] or #[demo(input(name = "BassInput", ty = "struct"))]
+// #[demo(param(val = "10_000_f64"), input(from = "total_population"))]
+//                      BassInput::total_population replaces the val of a param or stock
+// #[demo(output_name = "BassOutput")] or #[demo(output(name = "BassOutput", ty = "struct"))]
+// #[demo(stock, output(to = "clients"))]     BassOutput::clients is the field after each step
+// Without a name it is `{ident}Input` or `{ident}Output`, no struct without a name or fields.
+
+/// A generated struct and the model fields behind its fields.
+pub(crate) struct Port {
+    pub(crate) ident: syn::Ident,
+    /// `(port field, model field)`, in declaration order.
+    pub(crate) fields: Vec<(syn::Ident, syn::Ident)>,
+}
+
+const INPUT: &str = "input";
+const OUTPUT: &str = "output";
+
+/// The Input of `model`, its fields with `input(from)`.
+pub(crate) fn input_port(cont: &Container, model: &Model) -> syn::Result<Option<Port>> {
+    let mut fields = vec![];
+    for field in cont.fields_with(INPUT) {
+        let span = key_span(field, INPUT);
+        let ident = match &field.member {
+            syn::Member::Named(ident) => ident,
+            syn::Member::Unnamed(_) => continue,
+        };
+        let from = match field.attr(INPUT)?.get_path("from") {
+            Ok(Val::Str(from)) => port_field(from, span)?,
+            _ => return Err(syn::Error::new(span, "expect input(from = \"name\") #input_port")),
+        };
+        let role = model.params.iter().chain(&model.stocks).any(|node| node.ident == *ident);
+        if !role {
+            let msg = format!("`{ident}` is no param or stock, input(from) sets their val #input_port");
+            return Err(syn::Error::new(span, msg));
+        }
+        fields.push((from, ident.clone()));
+    }
+    port(cont, INPUT, fields)
+}
+
+/// The Output of the model, its fields with `output(to)`.
+pub(crate) fn output_port(cont: &Container) -> syn::Result<Option<Port>> {
+    let mut fields: Vec<(syn::Ident, syn::Ident)> = vec![];
+    for field in cont.fields_with(OUTPUT) {
+        let span = key_span(field, OUTPUT);
+        let ident = match &field.member {
+            syn::Member::Named(ident) => ident,
+            syn::Member::Unnamed(_) => continue,
+        };
+        for output in field.attr(OUTPUT)?.as_slice() {
+            let to = match output.get_path("to") {
+                Ok(Val::Str(to)) => port_field(to, span)?,
+                _ => return Err(syn::Error::new(span, "expect output(to = \"name\") #output_port")),
+            };
+            if fields.iter().any(|(name, _)| *name == to) {
+                let msg = format!("output(to = \"{to}\") given twice #output_port");
+                return Err(syn::Error::new(span, msg));
+            }
+            fields.push((to, ident.clone()));
+        }
+    }
+    port(cont, OUTPUT, fields)
+}
+
+fn port_field(name: &str, span: Span) -> syn::Result<syn::Ident> {
+    syn::parse_str(name).map_err(|_| syn::Error::new(span, format!("`{name}` is no field name")))
+}
+
+// The name by `{key}_name` or `{key}(name)` of the container.
+fn port(
+    cont: &Container,
+    key: &str,
+    fields: Vec<(syn::Ident, syn::Ident)>,
+) -> syn::Result<Option<Port>> {
+    let span = cont
+        .entries
+        .iter()
+        .find(|entry| entry.key == key || entry.key == format!("{key}_name"))
+        .map(|entry| entry.span)
+        .unwrap_or_else(|| cont.ident.span());
+    let by_name = match cont.attr(&format!("{key}_name")) {
+        Ok(Val::Str(name)) => Some(name.as_str()),
+        _ => None,
+    };
+    let by_map = match cont.attr(&format!("{key}.name")) {
+        Ok(Val::Str(name)) => Some(name.as_str()),
+        _ => None,
+    };
+    if let Ok(Val::Str(ty)) = cont.attr(&format!("{key}.ty")) {
+        if ty != "struct" {
+            let msg = format!("{key}(ty = \"{ty}\") is not supported, only \"struct\" #port");
+            return Err(syn::Error::new(span, msg));
+        }
+    }
+    let ident = match (by_name, by_map) {
+        (Some(a), Some(b)) if a != b => {
+            let msg = format!("{key}_name = \"{a}\" and {key}(name = \"{b}\") differ #port");
+            return Err(syn::Error::new(span, msg));
+        }
+        (Some(name), _) | (None, Some(name)) => syn::parse_str(name)
+            .map_err(|_| syn::Error::new(span, format!("`{name}` is no type name #port")))?,
+        (None, None) if fields.is_empty() => return Ok(None),
+        (None, None) if key == INPUT => format_ident!("{}Input", cont.ident),
+        (None, None) => format_ident!("{}Output", cont.ident),
+    };
+    Ok(Some(Port { ident, fields }))
+}
+
+/// The Input and Output structs, `from_input` and `output` of the model.
+pub(crate) fn io_tokens(
+    cont: &Container,
+    model: &Model,
+    input: Option<&Port>,
+    output: Option<&Port>,
+) -> TokenStream {
+    let vis = &cont.original.vis;
+    let mut tokens = TokenStream::new();
+    if let Some(Port { ident, fields }) = input {
+        // two fields may take the same input.
+        let mut names = vec![];
+        for (name, _) in fields {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        // mixed_site, a param named `input` does not shadow the argument.
+        let input = syn::Ident::new("input", Span::mixed_site());
+        let overrides: HashMap<_, _> = fields
+            .iter()
+            .map(|(name, field)| (field.clone(), quote!(#input.#name)))
+            .collect();
+        let init = init_tokens(model, &overrides);
+        let doc = format!("The input of [`{}`].", cont.ident);
+        tokens.extend(quote! {
+            #[doc = #doc]
+            #[derive(Debug, Clone, Copy, PartialEq, Default)]
+            #vis struct #ident {
+                #(pub #names: f64,)*
+            }
+        });
+        tokens.extend(cont.impl_inherent(quote! {
+            /// The model with the params and stocks `input` gives, the others at their defaults.
+            #[allow(unused_variables)]
+            pub fn from_input(#input: &#ident) -> Self {
+                #init
+            }
+        }));
+    }
+    if let Some(Port { ident, fields }) = output {
+        let names: Vec<_> = fields.iter().map(|(name, _)| name).collect();
+        let vals = fields.iter().map(|(_, field)| field);
+        let doc = format!("The output of [`{}`].", cont.ident);
+        tokens.extend(quote! {
+            #[doc = #doc]
+            #[derive(Debug, Clone, Copy, PartialEq, Default)]
+            #vis struct #ident {
+                #(pub #names: f64,)*
+            }
+        });
+        tokens.extend(cont.impl_inherent(quote! {
+            /// The output at the current state, eg. after each `step`.
+            pub fn output(&self) -> #ident {
+                #ident {
+                    #(#names: self.#vals,)*
+                }
+            }
+        }));
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use derive_attr_parser::{from_ast, Ctxt, Symbol};
+
+    use super::*;
+
+    // `(struct, [(port field, model field)])` of the input and the output.
+    type Ports = (Option<(String, Vec<(String, String)>)>, Option<(String, Vec<(String, String)>)>);
+
+    fn ports_of(input: syn::DeriveInput) -> syn::Result<Ports> {
+        let cx = Ctxt::new();
+        let cont = from_ast(&cx, &input, Symbol("demo"));
+        cx.check()?;
+        let cont = cont?;
+        let model = Model::from_container(&cont)?;
+        let names = |port: Option<Port>| {
+            port.map(|Port { ident, fields }| {
+                let fields = fields.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect();
+                (ident.to_string(), fields)
+            })
+        };
+        Ok((names(input_port(&cont, &model)?), names(output_port(&cont)?)))
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect()
+    }
+
+    #[test]
+    fn ports_map_the_fields() {
+        let input = syn::parse_quote! {
+            #[demo(input_name = "BassIn", output(name = "BassOut", ty = "struct"))]
+            struct Bass {
+                #[demo(param(val = "1.0"), input(from = "total"))]
+                total_population: f64,
+                #[demo(stock(val = "total_population"), input(from = "total"), output(to = "potential"))]
+                potential_clients: f64,
+                #[demo(stock(val = "0.0"), output(to = "clients"), output(to = "adopters"))]
+                clients: f64,
+                #[demo(flow(from = "potential_clients", to = "clients", val = "1.0"))]
+                sales: f64,
+            }
+        };
+        let (input, output) = ports_of(input).unwrap();
+        let input = input.unwrap();
+        assert_eq!(input.0, "BassIn");
+        assert_eq!(input.1, pairs(&[("total", "total_population"), ("total", "potential_clients")]));
+        let output = output.unwrap();
+        assert_eq!(output.0, "BassOut");
+        assert_eq!(
+            output.1,
+            pairs(&[("potential", "potential_clients"), ("clients", "clients"), ("adopters", "clients")])
+        );
+    }
+
+    #[test]
+    fn default_names_and_no_ports() {
+        let input = syn::parse_quote! {
+            struct Decay {
+                #[demo(param(val = "0.1"), input(from = "rate"))]
+                rate: f64,
+                #[demo(stock(val = "1.0"))]
+                level: f64,
+            }
+        };
+        let (input, output) = ports_of(input).unwrap();
+        assert_eq!(input.unwrap().0, "DecayInput");
+        assert!(output.is_none());
+        let input = syn::parse_quote! {
+            #[demo(output_name = "DecayOut")]
+            struct Decay {
+                #[demo(stock(val = "1.0"))]
+                level: f64,
+            }
+        };
+        let (input, output) = ports_of(input).unwrap();
+        assert!(input.is_none());
+        // a name alone gives an empty struct.
+        assert_eq!(output.unwrap(), ("DecayOut".to_string(), vec![]));
+    }
+
+    #[test]
+    fn port_errors() {
+        let err = |input: syn::DeriveInput| ports_of(input).err().unwrap().to_string();
+        let input = syn::parse_quote! {
+            struct Bass {
+                #[demo(var(val = "1.0"), input(from = "a"))]
+                a: f64,
+            }
+        };
+        assert_eq!(err(input), "`a` is no param or stock, input(from) sets their val #input_port");
+        let input = syn::parse_quote! {
+            struct Bass {
+                #[demo(stock, output(to = "a"))]
+                a: f64,
+                #[demo(stock, output(to = "a"))]
+                b: f64,
+            }
+        };
+        assert_eq!(err(input), "output(to = \"a\") given twice #output_port");
+        let input = syn::parse_quote! {
+            struct Bass {
+                #[demo(stock, output)]
+                a: f64,
+            }
+        };
+        assert_eq!(err(input), "expect output(to = \"name\") #output_port");
+        let input = syn::parse_quote! {
+            #[demo(input_name = "A", input(name = "B"))]
+            struct Bass {
+                #[demo(stock, input(from = "a"))]
+                a: f64,
+            }
+        };
+        assert_eq!(err(input), "input_name = \"A\" and input(name = \"B\") differ #port");
+        let input = syn::parse_quote! {
+            #[demo(output(ty = "tuple"))]
+            struct Bass {
+                #[demo(stock, output(to = "a"))]
+                a: f64,
+            }
+        };
+        assert_eq!(err(input), "output(ty = \"tuple\") is not supported, only \"struct\" #port");
+    }
+}
@@ -1,10 +1,33 @@
+//! Derive system dynamics models and finite state machines from `#[demo(..)]` attributes.
+//!
+//! Every `DemoDerive` expansion implements `derive_attr_runtime::AttrReflect`, so a crate using
+//! the derive depends on `derive-attr-runtime` too, or names a re-export of it with
+//! `#[demo(crate = "my_crate::runtime")]`.
 extern crate proc_macro;
 
-use derive_attr_parser::{from_ast, Ctxt, Symbol};
+mod composite;
+mod diagram;
+mod graph;
+mod import;
+mod io;
+mod series;
+mod sim;
+mod solver;
+mod xml;
+mod xmile;
+
+use composite::composite_checks;
+use diagram::diagram_tokens;
+use derive_attr_parser::{from_ast, from_ast_with, Ctxt, Options, Symbol, Val};
+use import::{sim_model_expand, SimModel};
+use io::{input_port, io_tokens, output_port};
 use quote::quote;
+use series::{recorded, series_tokens};
+use sim::{sim_tokens, Model};
 use syn::{parse_macro_input, DeriveInput};
+use xmile::xmile_tokens;
 
-#[proc_macro_derive(DemoDerive, attributes(demo))]
+#[proc_macro_derive(DemoDerive, attributes(demo, sim))]
 pub fn simuples(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let mut input = parse_macro_input!(input as DeriveInput);
     sim_expand(&mut input)
@@ -12,6 +35,15 @@ pub fn simuples(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
         .into()
 }
 
+/// A `DemoDerive` model struct from an XMILE file, `sim_model!("models/bass.xmile")`.
+#[proc_macro]
+pub fn sim_model(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as SimModel);
+    sim_model_expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
 #[proc_macro_derive(Fsm, attributes(fsm))]
 pub fn fsm_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let mut derive_input = syn::parse_macro_input!(input as syn::DeriveInput);
@@ -21,25 +53,52 @@ pub fn fsm_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
         .into()
 }
 
+const DEMO: Symbol = Symbol("demo");
+// the former root, still accepted.
 const SIM: Symbol = Symbol("sim");
+const INCLUDE: Symbol = Symbol("include");
 fn sim_expand(input: &mut syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let ctx = Ctxt::new();
-    let cont = from_ast(&ctx, input, SIM);
+    let opts = Options {
+        include_key: Some(INCLUDE),
+        aliases: vec![SIM],
+        helpers: vec![DEMO, SIM],
+        ..Options::default()
+    };
+    let cont = from_ast_with(&ctx, input, DEMO, &opts);
     ctx.check()?;
-    eprintln!("{cont:#?}");
+    let cont = cont?;
 
-    //Do something with the info. In the case, generate System Dynamic Code.
-    Ok(quote!())
+    let runtime = cont.crate_path("::derive_attr_runtime")?;
+    let includes = cont.include_tokens();
+    let reflect = cont.reflect_tokens(&runtime);
+    let checks = match cont.attrs.get("method") {
+        Some(Val::Str(method)) if method == "composited" => composite_checks(&cont, &runtime)?,
+        Some(Val::Str(method)) if method == "system_dynamics" => {
+            let model = Model::from_container(&cont)?;
+            let sim = sim_tokens(&cont, &model)?;
+            let input = input_port(&cont, &model)?;
+            let output = output_port(&cont)?;
+            let io = io_tokens(&cont, &model, input.as_ref(), output.as_ref());
+            let series = series_tokens(&cont, &recorded(&cont, &model)?);
+            let xmile = xmile_tokens(&cont, &model)?;
+            let diagram = diagram_tokens(&cont, &model)?;
+            quote!(#sim #io #series #xmile #diagram)
+        }
+        _ => quote!(),
+    };
+    Ok(quote! {
+        #includes
+        #reflect
+        #checks
+    })
 }
 
 const FSM: Symbol = Symbol("fsm");
 
 pub(crate) fn fsm_expand(input: &mut syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let ctx = Ctxt::new();
-    let cont = from_ast(&ctx, input, FSM);
-
-    eprintln!("{cont:#?}");
-
+    let _cont = from_ast(&ctx, input, FSM);
     ctx.check()?;
 
     //Do something with the info. In the case, generate FSM Code.
@@ -0,0 +1,203 @@
+use derive_attr_parser::{Container, Val};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::sim::{key_span, Model};
+
+// `run(start, stop, dt)` records a column per field into `{ident}Series`:
+// all the stocks, flows and vars by default, in declaration order,
+// #[demo(record)] on fields        only these fields (params too)
+// #[demo(record = "output")]       on the container, only the fields with output(to)
+// both together record the fields either selects.
+// `time` is the first column, a recorded field cannot be named so.
+
+const RECORD: &str = "record";
+
+/// The fields `run` records.
+pub(crate) fn recorded(cont: &Container, model: &Model) -> syn::Result<Vec<syn::Ident>> {
+    let outputs = match cont.attr(RECORD) {
+        Ok(Val::Str(record)) if record == "output" => true,
+        Ok(_) => {
+            let span = cont
+                .entries
+                .iter()
+                .find(|entry| entry.key == RECORD)
+                .map(|entry| entry.span)
+                .unwrap_or_else(|| cont.ident.span());
+            return Err(syn::Error::new(span, "expect record = \"output\" #recorded"));
+        }
+        Err(_) => false,
+    };
+    let marked = cont.fields_with(RECORD).next().is_some();
+    let mut fields = vec![];
+    for field in cont.data.all_fields() {
+        let ident = match &field.member {
+            syn::Member::Named(ident) => ident,
+            syn::Member::Unnamed(_) => continue,
+        };
+        let param = model.params.iter().any(|node| node.ident == *ident);
+        let stock = model.stocks.iter().any(|node| node.ident == *ident);
+        let computed = model.computed().iter().any(|node| node.ident == *ident);
+        let record = field.attrs.contains_key(RECORD);
+        if record && !(param || stock || computed) {
+            let msg = format!("`{ident}` has no role, only params, stocks, vars and flows record");
+            return Err(syn::Error::new(key_span(field, RECORD), msg));
+        }
+        let selected = match (marked, outputs) {
+            (false, false) => stock || computed,
+            _ => record || outputs && field.attrs.contains_key("output"),
+        };
+        if selected && ident == "time" {
+            let msg = "a recorded field cannot be named `time`, the first column of the series";
+            return Err(syn::Error::new(ident.span(), format!("{msg} #recorded")));
+        }
+        if selected {
+            fields.push(ident.clone());
+        }
+    }
+    Ok(fields)
+}
+
+/// `{ident}Series` with `write_csv`, and `run` of the model.
+///
+/// The columns are f64 whatever the generics of the model, only `run` is generic.
+pub(crate) fn series_tokens(cont: &Container, fields: &[syn::Ident]) -> TokenStream {
+    let vis = &cont.original.vis;
+    let model = &cont.ident;
+    let series = format_ident!("{}Series", model);
+    let n = fields.len() + 1;
+    let names = fields.iter().map(|field| field.to_string());
+    let doc = format!("The fields of [`{model}`] over time, by [`{model}::run`].");
+
+    let mut tokens = quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, PartialEq, Default)]
+        #vis struct #series {
+            pub time: ::std::vec::Vec<f64>,
+            #(pub #fields: ::std::vec::Vec<f64>,)*
+        }
+
+        impl #series {
+            /// The CSV header, `time` first.
+            pub const COLUMNS: [&'static str; #n] = ["time", #(#names),*];
+
+            /// The number of rows.
+            pub fn len(&self) -> usize {
+                self.time.len()
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.time.is_empty()
+            }
+
+            /// Write the header and a row per time, comma separated.
+            pub fn write_csv<W: ::std::io::Write>(&self, mut writer: W) -> ::std::io::Result<()> {
+                writeln!(writer, "{}", Self::COLUMNS.join(","))?;
+                for row in 0..self.len() {
+                    write!(writer, "{}", self.time[row])?;
+                    #(write!(writer, ",{}", self.#fields[row])?;)*
+                    writeln!(writer)?;
+                }
+                Ok(())
+            }
+        }
+    };
+    tokens.extend(cont.impl_inherent(quote! {
+        /// Step from `start` to `stop` by `dt`, a row at `start`, after each step and at `stop`.
+        ///
+        /// The last step is shorter when `dt` does not divide `stop - start`. `None` unless the
+        /// arguments are finite, `dt > 0` and `start <= stop`, the model is left untouched then.
+        pub fn run(&mut self, start: f64, stop: f64, dt: f64) -> ::std::option::Option<#series> {
+            let finite = start.is_finite() && stop.is_finite() && dt.is_finite();
+            if !(finite && dt > 0.0 && start <= stop) {
+                return ::std::option::Option::None;
+            }
+            let mut series = #series::default();
+            series.time.push(start);
+            #(series.#fields.push(self.#fields);)*
+            let mut time = start;
+            let mut steps = 0_u64;
+            while stop - time > dt * 1e-9 {
+                steps += 1;
+                // from `start` each time, summing up dt would drift.
+                let next = (start + steps as f64 * dt).min(stop);
+                self.step(next - time);
+                time = next;
+                series.time.push(time);
+                #(series.#fields.push(self.#fields);)*
+            }
+            ::std::option::Option::Some(series)
+        }
+    }));
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use derive_attr_parser::{from_ast, Ctxt, Symbol};
+
+    use super::*;
+
+    fn recorded_of(input: syn::DeriveInput) -> syn::Result<Vec<String>> {
+        let cx = Ctxt::new();
+        let cont = from_ast(&cx, &input, Symbol("demo"));
+        cx.check()?;
+        let cont = cont?;
+        let model = Model::from_container(&cont)?;
+        Ok(recorded(&cont, &model)?.iter().map(ToString::to_string).collect())
+    }
+
+    #[test]
+    fn selection() {
+        let input = syn::parse_quote! {
+            struct Decay {
+                #[demo(param(val = "0.1"))]
+                rate: f64,
+                #[demo(stock(val = "1.0"), output(to = "level"))]
+                level: f64,
+                #[demo(flow(from = "level", val = "level * rate"))]
+                decay: f64,
+            }
+        };
+        assert_eq!(recorded_of(input).unwrap(), ["level", "decay"]);
+        let input = syn::parse_quote! {
+            #[demo(record = "output")]
+            struct Decay {
+                #[demo(param(val = "0.1"), record)]
+                rate: f64,
+                #[demo(stock(val = "1.0"), output(to = "level"))]
+                level: f64,
+                #[demo(flow(from = "level", val = "level * rate"))]
+                decay: f64,
+            }
+        };
+        assert_eq!(recorded_of(input).unwrap(), ["rate", "level"]);
+    }
+
+    #[test]
+    fn time_is_taken() {
+        let input = syn::parse_quote! {
+            struct Clock {
+                #[demo(stock(val = "0.0"))]
+                time: f64,
+                #[demo(flow(to = "time", val = "1.0"))]
+                tick: f64,
+            }
+        };
+        let err = recorded_of(input).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "a recorded field cannot be named `time`, the first column of the series #recorded"
+        );
+        // left out of record, `time` is fine.
+        let input = syn::parse_quote! {
+            struct Clock {
+                #[demo(stock(val = "0.0"))]
+                time: f64,
+                #[demo(flow(to = "time", val = "1.0"), record)]
+                tick: f64,
+            }
+        };
+        assert_eq!(recorded_of(input).unwrap(), ["tick"]);
+    }
+}
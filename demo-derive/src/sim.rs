@@ -0,0 +1,490 @@
+use std::collections::HashMap;
+
+use derive_attr_parser::{Container, Field, Val};
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::{quote, quote_spanned, ToTokens};
+use syn::spanned::Spanned;
+
+use crate::graph::eval_order;
+use crate::solver::Solver;
+
+// method = "system_dynamics": every f64 field plays one role, the struct itself is the model.
+// #[demo(param(val = "0.015_f64"))]              a constant, `val` is the default
+// #[demo(stock(val = "total_population"))]       a level, `val` is the initial value, 0 if absent
+// #[demo(var(val = "potential_clients * ad_effectiveness"))]  an auxiliary computed each step
+// #[demo(flow(from = "potential_clients", to = "clients", val = "sales_from_ad + sales_from_wom"))]
+//                                                a rate draining `from` and filling `to`
+// the `val` expressions use the other fields by name. `from` and `to` name stocks, a flow without
+// `from` comes from a cloud (a source outside the model), without `to` it goes to a cloud (a sink).
+// A stock no flow touches stays at its initial value, the derive warns about it.
+// A number from an include, `val = 0.5` in TOML, is the f64 literal.
+
+/// A field with a role, `val` parsed as expression.
+pub(crate) struct Node {
+    pub(crate) ident: syn::Ident,
+    pub(crate) val: Option<syn::Expr>,
+    /// The role key, eg. `var` of `var(val = "..")`.
+    pub(crate) span: Span,
+    /// The `val` string as written, `span` when it comes from an include.
+    pub(crate) val_span: Span,
+}
+
+pub(crate) struct Flow {
+    pub(crate) node: Node,
+    pub(crate) from: Option<syn::Ident>,
+    pub(crate) to: Option<syn::Ident>,
+}
+
+/// The fields of a system dynamics model by role, in declaration order.
+pub(crate) struct Model {
+    pub(crate) params: Vec<Node>,
+    pub(crate) stocks: Vec<Node>,
+    pub(crate) vars: Vec<Node>,
+    pub(crate) flows: Vec<Flow>,
+    /// Fields without a role, `Default::default()` in `new`.
+    pub(crate) others: Vec<syn::Ident>,
+    pub(crate) solver: Solver,
+    /// The order of [`Model::initials`] in `new`.
+    pub(crate) init_order: Vec<usize>,
+    /// The order of [`Model::computed`] each evaluation.
+    pub(crate) eval_order: Vec<usize>,
+}
+
+const PARAM: &str = "param";
+const STOCK: &str = "stock";
+const VAR: &str = "var";
+const FLOW: &str = "flow";
+const ROLES: [&str; 4] = [PARAM, STOCK, VAR, FLOW];
+
+impl Model {
+    pub(crate) fn from_container(cont: &Container) -> syn::Result<Model> {
+        // from_ast rejects unions already.
+        if let syn::Data::Enum(data) = &cont.original.data {
+            let msg = "system_dynamics models are structs, the fields are the model state";
+            return Err(syn::Error::new(data.enum_token.span(), msg));
+        }
+        let mut model = Model {
+            params: vec![],
+            stocks: vec![],
+            vars: vec![],
+            flows: vec![],
+            others: vec![],
+            solver: Solver::from_container(cont)?,
+            init_order: vec![],
+            eval_order: vec![],
+        };
+        for field in cont.data.all_fields() {
+            let ident = match &field.member {
+                syn::Member::Named(ident) => ident.clone(),
+                syn::Member::Unnamed(_) => {
+                    let msg = "system_dynamics models need named fields";
+                    return Err(syn::Error::new(field.member.span(), msg));
+                }
+            };
+            let roles: Vec<_> =
+                ROLES.into_iter().filter(|role| field.attrs.contains_key(*role)).collect();
+            if let [first, second, ..] = roles[..] {
+                let msg = format!("`{ident}` is both {first} and {second}, a field has one role");
+                return Err(syn::Error::new(key_span(field, second), msg));
+            }
+            if let Some(role) = roles.first() {
+                let f64 = matches!(field.ty, syn::Type::Path(ty) if ty.path.is_ident("f64"));
+                if !f64 {
+                    let ty = field.ty.to_token_stream();
+                    let msg = format!("`{ident}` is a {role} of type `{ty}`, a field with a role is f64");
+                    return Err(syn::Error::new(field.ty.span(), msg));
+                }
+            }
+            if field.attrs.contains_key(PARAM) {
+                model.params.push(node(field, PARAM, ident)?);
+            } else if field.attrs.contains_key(STOCK) {
+                model.stocks.push(node(field, STOCK, ident)?);
+            } else if field.attrs.contains_key(VAR) {
+                model.vars.push(node(field, VAR, ident)?);
+            } else if field.attrs.contains_key(FLOW) {
+                let from = flow_end(field, "from")?;
+                let to = flow_end(field, "to")?;
+                let node = node(field, FLOW, ident)?;
+                if from.is_none() && to.is_none() {
+                    let msg = format!("`{}` flows from cloud to cloud, give from or to", node.ident);
+                    return Err(syn::Error::new(node.span, msg));
+                }
+                model.flows.push(Flow { node, from, to });
+            } else {
+                model.others.push(ident);
+            }
+        }
+        model.check_flow_ends()?;
+        let initials = model.initials();
+        let computed = model.computed();
+        let (stated, unstated) = (idents(&initials), idents(&computed));
+        let others: Vec<_> = model.others.iter().collect();
+        // the initial vals use params and stocks, the others use all but the fields without role.
+        let init_order = eval_order(&initials, &[], &[&unstated[..], &others[..]].concat())?;
+        let eval_order = eval_order(&computed, &stated, &others)?;
+        Ok(Model {
+            init_order,
+            eval_order,
+            ..model
+        })
+    }
+
+    // Every `from` and `to` is a stock.
+    fn check_flow_ends(&self) -> syn::Result<()> {
+        let ends = self.flows.iter().flat_map(|flow| [("from", &flow.from), ("to", &flow.to)]);
+        for (end, name) in ends.filter_map(|(end, name)| Some((end, name.as_ref()?))) {
+            if self.stocks.iter().any(|stock| stock.ident == *name) {
+                continue;
+            }
+            let role = [(PARAM, &self.params), (VAR, &self.vars)]
+                .into_iter()
+                .find(|(_, nodes)| nodes.iter().any(|node| node.ident == *name))
+                .map(|(role, _)| role)
+                .or_else(|| self.flows.iter().any(|flow| flow.node.ident == *name).then_some(FLOW));
+            let msg = match role {
+                Some(role) => format!("`{name}` is a {role}, flow({end}) needs a stock"),
+                None => format!("no stock `{name}` for flow({end})"),
+            };
+            return Err(syn::Error::new(name.span(), msg));
+        }
+        Ok(())
+    }
+
+    /// The params and stocks, their vals give the initial state.
+    pub(crate) fn initials(&self) -> Vec<&Node> {
+        self.params.iter().chain(&self.stocks).collect()
+    }
+
+    /// The vars and flows, their vals follow the state.
+    pub(crate) fn computed(&self) -> Vec<&Node> {
+        self.vars.iter().chain(self.flows.iter().map(|flow| &flow.node)).collect()
+    }
+}
+
+fn idents<'n>(nodes: &[&'n Node]) -> Vec<&'n syn::Ident> {
+    nodes.iter().map(|node| &node.ident).collect()
+}
+
+/// The span of `key` in the attrs of `field`.
+pub(crate) fn key_span(field: &Field, key: &str) -> Span {
+    field
+        .entries
+        .iter()
+        .find(|entry| entry.key == key)
+        .map(|entry| entry.span)
+        .unwrap_or_else(|| field.member.span())
+}
+
+fn node(field: &Field, role: &str, ident: syn::Ident) -> syn::Result<Node> {
+    let span = key_span(field, role);
+    let mut val_span = span;
+    let val = match field.attr(&format!("{role}.val")) {
+        Ok(Val::Str(s)) => {
+            val_span = lit_span(field, s).unwrap_or(span);
+            let expr = Val::Str(s.clone()).as_expr();
+            Some(expr.map_err(|err| syn::Error::new(val_span, err))?)
+        }
+        Ok(Val::Int(i)) => Some(number(*i as f64, span)),
+        Ok(Val::Float(f)) if f.is_finite() => Some(number(*f, span)),
+        Ok(_) => {
+            let msg = format!("expect {role}(val = \"expression\") or a finite number");
+            return Err(syn::Error::new(span, msg));
+        }
+        Err(_) => None,
+    };
+    Ok(Node { ident, val, span, val_span })
+}
+
+fn number(val: f64, span: Span) -> syn::Expr {
+    let mut lit = proc_macro2::Literal::f64_suffixed(val);
+    lit.set_span(span);
+    syn::parse_quote!(#lit)
+}
+
+// The string literal `value` among the attrs of the field.
+fn lit_span(field: &Field, value: &str) -> Option<Span> {
+    fn find(tokens: TokenStream, value: &str) -> Option<Span> {
+        tokens.into_iter().find_map(|token| match token {
+            TokenTree::Group(group) => find(group.stream(), value),
+            TokenTree::Literal(lit) => syn::parse2::<syn::LitStr>(lit.into_token_stream())
+                .ok()
+                .filter(|lit| lit.value() == value)
+                .map(|lit| lit.span()),
+            _ => None,
+        })
+    }
+    field
+        .original
+        .attrs
+        .iter()
+        .find_map(|attr| find(attr.meta.to_token_stream(), value))
+}
+
+// The stock named by `from` or `to`, spanned at the name as written.
+fn flow_end(field: &Field, end: &str) -> syn::Result<Option<syn::Ident>> {
+    match field.attr(&format!("{FLOW}.{end}")) {
+        Ok(Val::Str(name)) => {
+            let span = lit_span(field, name).unwrap_or_else(|| key_span(field, FLOW));
+            let mut ident: syn::Ident =
+                syn::parse_str(name).map_err(|err| syn::Error::new(span, err))?;
+            ident.set_span(span);
+            Ok(Some(ident))
+        }
+        _ => Ok(None),
+    }
+}
+
+// A `#[deprecated]` const used at the stock key, the only way to warn on stable.
+fn stock_warnings(model: &Model) -> TokenStream {
+    let unused = model.stocks.iter().filter(|stock| {
+        !model.flows.iter().any(|flow| {
+            flow.from.as_ref() == Some(&stock.ident) || flow.to.as_ref() == Some(&stock.ident)
+        })
+    });
+    let warnings = unused.map(|stock| {
+        let note = format!("stock `{}` has no inflow or outflow, it stays constant", stock.ident);
+        let use_it = quote_spanned!(stock.span=> let _ = stock_without_flow;);
+        quote! {
+            const _: () = {
+                #[deprecated(note = #note)]
+                #[allow(non_upper_case_globals)]
+                const stock_without_flow: () = ();
+                #use_it
+            };
+        }
+    });
+    quote!(#(#warnings)*)
+}
+
+/// `impl Model { new, state, set_state, derivatives, update, step }`.
+pub(crate) fn sim_tokens(cont: &Container, model: &Model) -> syn::Result<TokenStream> {
+    let n = model.stocks.len();
+    let stock_names = model.stocks.iter().map(|stock| stock.ident.to_string());
+    let stocks: Vec<_> = model.stocks.iter().map(|stock| &stock.ident).collect();
+    let indexes: Vec<_> = (0..n).map(syn::Index::from).collect();
+
+    let vars: Vec<_> = model.vars.iter().map(|var| &var.ident).collect();
+    let flows: Vec<_> = model.flows.iter().map(|flow| &flow.node.ident).collect();
+
+    let init = init_tokens(model, &HashMap::new());
+    let eval = eval_tokens(model)?;
+    let step = model.solver.step_tokens(n);
+    let derivatives = model.stocks.iter().map(|stock| {
+        let inflows = model
+            .flows
+            .iter()
+            .filter(|flow| flow.to.as_ref() == Some(&stock.ident))
+            .map(|flow| &flow.node.ident);
+        let outflows = model
+            .flows
+            .iter()
+            .filter(|flow| flow.from.as_ref() == Some(&stock.ident))
+            .map(|flow| &flow.node.ident);
+        quote!(0.0 #(+ #inflows)* #(- #outflows)*)
+    });
+
+    let warnings = stock_warnings(model);
+    // mixed_site, a stock named `state` does not shadow the param.
+    let state = syn::Ident::new("state", Span::mixed_site());
+    let methods = cont.impl_inherent(quote! {
+        /// The stocks, in the order of the state vector.
+        pub const STOCKS: [&'static str; #n] = [#(#stock_names),*];
+
+        /// The model with the params at their defaults and the stocks at their initial values.
+        #[allow(unused_variables)]
+        pub fn new() -> Self {
+            #init
+        }
+
+        /// The stocks as state vector.
+        pub fn state(&self) -> [f64; #n] {
+            [#(self.#stocks),*]
+        }
+
+        /// Set the stocks from the state vector, the vars and flows follow.
+        pub fn set_state(&mut self, state: [f64; #n]) {
+            #(self.#stocks = state[#indexes];)*
+            self.update();
+        }
+
+        /// The rate of change of each stock at `state`, its inflows minus its outflows.
+        #[allow(unused_variables)]
+        pub fn derivatives(&self, #state: &[f64; #n]) -> [f64; #n] {
+            #(let #stocks = #state[#indexes];)*
+            #eval
+            [#(#derivatives),*]
+        }
+
+        /// Recompute the vars and flows from the params and stocks.
+        #[allow(unused_variables)]
+        pub fn update(&mut self) {
+            #(let #stocks = self.#stocks;)*
+            #eval
+            #(self.#vars = #vars;)*
+            #(self.#flows = #flows;)*
+        }
+
+        /// Advance the model by `dt` with the `ode_solver`.
+        pub fn step(&mut self, dt: f64) {
+            #step
+        }
+    });
+    Ok(quote!(#methods #warnings))
+}
+
+/// The body of a constructor, `overrides` replace the `val` of params and stocks.
+pub(crate) fn init_tokens(model: &Model, overrides: &HashMap<syn::Ident, TokenStream>) -> TokenStream {
+    let val = |node: &Node| match (overrides.get(&node.ident), &node.val) {
+        (Some(val), _) => val.clone(),
+        (None, Some(val)) => quote!(#val),
+        (None, None) => quote!(0.0),
+    };
+    let initials = model.initials();
+    let lets = model.init_order.iter().map(|at| {
+        let ident = &initials[*at].ident;
+        let val = val(initials[*at]);
+        quote!(let #ident: f64 = #val;)
+    });
+    let params = model.params.iter().map(|param| &param.ident);
+    let stocks = model.stocks.iter().map(|stock| &stock.ident);
+    let vars = model.vars.iter().map(|var| &var.ident);
+    let flows = model.flows.iter().map(|flow| &flow.node.ident);
+    let others = &model.others;
+    let model = syn::Ident::new("model", Span::mixed_site());
+    quote! {
+        #(#lets)*
+        let mut #model = Self {
+            #(#params,)*
+            #(#stocks,)*
+            #(#vars: 0.0,)*
+            #(#flows: 0.0,)*
+            #(#others: ::core::default::Default::default(),)*
+        };
+        #model.update();
+        #model
+    }
+}
+
+// `let` the params, then the vars and flows in `eval_order`, the stocks are bound by the caller.
+fn eval_tokens(model: &Model) -> syn::Result<TokenStream> {
+    let params = model.params.iter().map(|param| &param.ident);
+    let computed = model.computed();
+    let mut lets = vec![];
+    for node in model.eval_order.iter().map(|at| computed[*at]) {
+        let ident = &node.ident;
+        let val = node.val.as_ref().ok_or_else(|| {
+            syn::Error::new(node.span, format!("`{ident}` needs val = \"expression\""))
+        })?;
+        lets.push(quote!(let #ident: f64 = #val;));
+    }
+    Ok(quote! {
+        #(let #params = self.#params;)*
+        #(#lets)*
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use derive_attr_parser::{from_ast, Ctxt, Symbol};
+
+    use super::*;
+
+    fn model_of(src: &str) -> syn::Result<Model> {
+        let input: syn::DeriveInput = syn::parse_str(src).unwrap();
+        let cx = Ctxt::new();
+        let cont = from_ast(&cx, &input, Symbol("demo"));
+        cx.check()?;
+        Model::from_container(&cont?)
+    }
+
+    // the message and the line, column it points at.
+    fn err_of(src: &str) -> (String, usize, usize) {
+        let err = model_of(src).err().unwrap();
+        let start = err.span().start();
+        (err.to_string(), start.line, start.column)
+    }
+
+    #[test]
+    fn clouds_and_roles() {
+        let model = model_of(
+            "struct Population {
+                #[demo(stock(val = \"100.0\"))]
+                people: f64,
+                #[demo(flow(from = \"people\", val = \"people * 0.01\"))]
+                deaths: f64,
+                #[demo(flow(to = \"people\", val = \"2.0\"))]
+                births: f64,
+                #[demo(var(val = \"births - deaths\"))]
+                net: f64,
+                #[demo(param(val = \"0.1\"))]
+                rate: f64,
+                label: String,
+            }",
+        )
+        .unwrap();
+        let name = |end: &Option<syn::Ident>| end.as_ref().map(ToString::to_string);
+        let ends: Vec<_> = model.flows.iter().map(|flow| (name(&flow.from), name(&flow.to))).collect();
+        assert_eq!(ends, [(Some("people".to_string()), None), (None, Some("people".to_string()))]);
+        assert_eq!((model.params.len(), model.stocks.len(), model.vars.len()), (1, 1, 1));
+        assert_eq!(model.others, ["label"]);
+        // every stock has a flow, no warning.
+        assert!(stock_warnings(&model).is_empty());
+    }
+
+    #[test]
+    fn flow_ends_are_stocks() {
+        let src = "struct A {
+    #[demo(param(val = \"1.0\"))]
+    rate: f64,
+    #[demo(flow(from = \"rate\", val = \"1.0\"))]
+    drain: f64,
+}";
+        assert_eq!(err_of(src), ("`rate` is a param, flow(from) needs a stock".to_string(), 4, 23));
+        let src = "struct A {
+    #[demo(stock)]
+    level: f64,
+    #[demo(flow(from = \"level\", to = \"levle\", val = \"1.0\"))]
+    drain: f64,
+}";
+        assert_eq!(err_of(src), ("no stock `levle` for flow(to)".to_string(), 4, 37));
+        let src = "struct A {
+    #[demo(flow(val = \"1.0\"))]
+    drain: f64,
+}";
+        let msg = "`drain` flows from cloud to cloud, give from or to";
+        assert_eq!(err_of(src), (msg.to_string(), 2, 11));
+    }
+
+    #[test]
+    fn one_role_per_field() {
+        let src = "struct A {
+    #[demo(stock, flow(to = \"a\"))]
+    a: f64,
+}";
+        assert_eq!(err_of(src), ("`a` is both stock and flow, a field has one role".to_string(), 2, 18));
+        let src = "struct A {
+    #[demo(stock)]
+    a: f32,
+}";
+        assert_eq!(err_of(src), ("`a` is a stock of type `f32`, a field with a role is f64".to_string(), 3, 7));
+    }
+
+    #[test]
+    fn stock_without_flow_warns() {
+        let model = model_of(
+            "struct A {
+                #[demo(stock(val = \"1.0\"))]
+                kept: f64,
+                #[demo(stock)]
+                level: f64,
+                #[demo(flow(to = \"level\", val = \"kept\"))]
+                fill: f64,
+            }",
+        )
+        .unwrap();
+        let warnings = stock_warnings(&model).to_string();
+        assert_eq!(warnings.matches("# [deprecated").count(), 1);
+        assert!(warnings.contains("stock `kept` has no inflow or outflow, it stays constant"));
+        assert!(!warnings.contains("`level`"));
+    }
+}
@@ -0,0 +1,243 @@
+use derive_attr_parser::{Container, Val};
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned};
+
+// The integration of `step(dt)`, by the ode_solver attr of the container:
+// #[demo(ode_solver = "rk4")]
+// #[demo(ode_solver(algo = "rk45", rtol = "1e-6", atol = "1e-9"))]
+// euler (or eula), heun and rk4 take one step of dt, rk45 (Dormand–Prince) takes as many
+// steps as the tolerances need to cover dt. Without ode_solver it is euler.
+
+/// An ODE solver for the stocks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Solver {
+    Euler,
+    Heun,
+    Rk4,
+    Rk45 { rtol: f64, atol: f64 },
+}
+
+const ODE_SOLVER: &str = "ode_solver";
+const ALGO: &str = "algo";
+const RTOL: &str = "rtol";
+const ATOL: &str = "atol";
+
+impl Solver {
+    pub(crate) fn from_container(cont: &Container) -> syn::Result<Solver> {
+        let span = cont
+            .entries
+            .iter()
+            .find(|entry| entry.key == ODE_SOLVER)
+            .map(|entry| entry.span)
+            .unwrap_or_else(|| cont.ident.span());
+        let (algo, opts) = match cont.attrs.get(ODE_SOLVER) {
+            None => return Ok(Solver::Euler),
+            Some(Val::Str(algo)) => (algo.as_str(), None),
+            Some(Val::Map(opts)) => match opts.get(ALGO) {
+                Some(Val::Str(algo)) => (algo.as_str(), Some(opts)),
+                _ => {
+                    let msg = format!("expect {ODE_SOLVER}(algo = \"name\") #Solver");
+                    return Err(syn::Error::new(span, msg));
+                }
+            },
+            Some(_) => {
+                let msg = format!("expect one {ODE_SOLVER} = \"name\" #Solver");
+                return Err(syn::Error::new(span, msg));
+            }
+        };
+        let tol = |key: &str, default: f64| -> syn::Result<f64> {
+            match opts.and_then(|opts| opts.get(key)) {
+                None => Ok(default),
+                // the step size control needs a positive tolerance to aim at.
+                Some(Val::Str(s)) => match s.parse::<f64>() {
+                    Ok(tol) if tol > 0.0 && tol.is_finite() => Ok(tol),
+                    Ok(_) => {
+                        let msg = format!("{key} = {s:?} must be a positive number #Solver");
+                        Err(syn::Error::new(span, msg))
+                    }
+                    Err(_) => {
+                        let msg = format!("{key} = {s:?} is not a number #Solver");
+                        Err(syn::Error::new(span, msg))
+                    }
+                },
+                Some(_) => Err(syn::Error::new(span, format!("expect {key} = \"number\" #Solver"))),
+            }
+        };
+        let solver = match algo {
+            "euler" | "eula" => Solver::Euler,
+            "heun" => Solver::Heun,
+            "rk4" => Solver::Rk4,
+            "rk45" => Solver::Rk45 {
+                rtol: tol(RTOL, 1e-6)?,
+                atol: tol(ATOL, 1e-9)?,
+            },
+            _ => {
+                let msg =
+                    format!("unknown {ODE_SOLVER} `{algo}`, expect euler, heun, rk4 or rk45 #Solver");
+                return Err(syn::Error::new(span, msg));
+            }
+        };
+        if !matches!(solver, Solver::Rk45 { .. }) {
+            let given = |key: &&str| opts.is_some_and(|opts| opts.contains_key(*key));
+            if let Some(key) = [RTOL, ATOL].into_iter().find(given) {
+                let msg = format!("{key} only applies to rk45, not to {algo} #Solver");
+                return Err(syn::Error::new(span, msg));
+            }
+        }
+        Ok(solver)
+    }
+
+    /// The body of `step(&mut self, dt: f64)` over a state of `n` stocks.
+    ///
+    /// The bindings are mixed_site, `self` and `dt` of `step` are bound to `model` and `dt` of
+    /// their own first, so no name of the model gets in between.
+    pub(crate) fn step_tokens(&self, n: usize) -> TokenStream {
+        let next = match *self {
+            Solver::Euler => quote_spanned! {Span::mixed_site()=>
+                let k1 = model.derivatives(&state);
+                let next = add(&state, dt, &[(1.0, &k1)]);
+            },
+            Solver::Heun => quote_spanned! {Span::mixed_site()=>
+                let k1 = model.derivatives(&state);
+                let k2 = model.derivatives(&add(&state, dt, &[(1.0, &k1)]));
+                let next = add(&state, dt, &[(0.5, &k1), (0.5, &k2)]);
+            },
+            Solver::Rk4 => quote_spanned! {Span::mixed_site()=>
+                let k1 = model.derivatives(&state);
+                let k2 = model.derivatives(&add(&state, dt, &[(0.5, &k1)]));
+                let k3 = model.derivatives(&add(&state, dt, &[(0.5, &k2)]));
+                let k4 = model.derivatives(&add(&state, dt, &[(1.0, &k3)]));
+                let next = add(&state, dt, &[
+                    (1.0 / 6.0, &k1), (1.0 / 3.0, &k2), (1.0 / 3.0, &k3), (1.0 / 6.0, &k4),
+                ]);
+            },
+            Solver::Rk45 { rtol, atol } => rk45_tokens(n, rtol, atol),
+        };
+        let bind = quote_spanned!(Span::mixed_site()=> let (model, dt) =);
+        let body = quote_spanned! {Span::mixed_site()=>
+            // `y + h * Σ c * k`
+            fn add<const N: usize>(y: &[f64; N], h: f64, ks: &[(f64, &[f64; N])]) -> [f64; N] {
+                let mut out = *y;
+                for (c, k) in ks {
+                    for (out, k) in out.iter_mut().zip(k.iter()) {
+                        *out += h * c * k;
+                    }
+                }
+                out
+            }
+            let state = model.state();
+            #next
+            model.set_state(next);
+        };
+        quote! {
+            #bind (self, dt);
+            #body
+        }
+    }
+}
+
+// Dormand–Prince 5(4), the error is the difference of the 5th and the embedded 4th order.
+fn rk45_tokens(n: usize, rtol: f64, atol: f64) -> TokenStream {
+    quote_spanned! {Span::mixed_site()=>
+        let (rtol, atol): (f64, f64) = (#rtol, #atol);
+        let mut y = state;
+        let mut t = 0.0_f64;
+        let mut h = dt;
+        while (dt - t).abs() > dt.abs() * 1e-12 {
+            h = if dt > 0.0 { h.min(dt - t) } else { h.max(dt - t) };
+            let k1 = model.derivatives(&y);
+            let k2 = model.derivatives(&add(&y, h, &[(1.0 / 5.0, &k1)]));
+            let k3 = model.derivatives(&add(&y, h, &[(3.0 / 40.0, &k1), (9.0 / 40.0, &k2)]));
+            let k4 = model.derivatives(&add(&y, h, &[
+                (44.0 / 45.0, &k1), (-56.0 / 15.0, &k2), (32.0 / 9.0, &k3),
+            ]));
+            let k5 = model.derivatives(&add(&y, h, &[
+                (19372.0 / 6561.0, &k1), (-25360.0 / 2187.0, &k2), (64448.0 / 6561.0, &k3), (-212.0 / 729.0, &k4),
+            ]));
+            let k6 = model.derivatives(&add(&y, h, &[
+                (9017.0 / 3168.0, &k1), (-355.0 / 33.0, &k2), (46732.0 / 5247.0, &k3), (49.0 / 176.0, &k4),
+                (-5103.0 / 18656.0, &k5),
+            ]));
+            let y5 = add(&y, h, &[
+                (35.0 / 384.0, &k1), (500.0 / 1113.0, &k3), (125.0 / 192.0, &k4), (-2187.0 / 6784.0, &k5),
+                (11.0 / 84.0, &k6),
+            ]);
+            let k7 = model.derivatives(&y5);
+            let diff = add(&[0.0; #n], h, &[
+                (71.0 / 57600.0, &k1), (-71.0 / 16695.0, &k3), (71.0 / 1920.0, &k4), (-17253.0 / 339200.0, &k5),
+                (22.0 / 525.0, &k6), (-1.0 / 40.0, &k7),
+            ]);
+            let mut err = 0.0_f64;
+            for ((diff, y), y5) in diff.iter().zip(y.iter()).zip(y5.iter()) {
+                err = err.max(diff.abs() / (atol + rtol * y.abs().max(y5.abs())));
+            }
+            // accept, or give up shrinking once h is negligible.
+            if err <= 1.0 || h.abs() <= dt.abs() * 1e-9 {
+                t += h;
+                y = y5;
+            }
+            h *= if err == 0.0 { 5.0 } else { (0.9 * err.powf(-0.2)).clamp(0.2, 5.0) };
+        }
+        let next = y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use derive_attr_parser::{from_ast, Ctxt, Symbol};
+
+    use super::*;
+
+    fn solver(input: syn::DeriveInput) -> syn::Result<Solver> {
+        let cx = Ctxt::new();
+        let cont = from_ast(&cx, &input, Symbol("demo"));
+        cx.check()?;
+        Solver::from_container(&cont?)
+    }
+
+    #[test]
+    fn algos() {
+        let cases = [
+            (syn::parse_quote!(struct A;), Solver::Euler),
+            (syn::parse_quote!(#[demo(ode_solver = "eula")] struct A;), Solver::Euler),
+            (syn::parse_quote!(#[demo(ode_solver = "heun")] struct A;), Solver::Heun),
+            (syn::parse_quote!(#[demo(ode_solver(algo = "rk4"))] struct A;), Solver::Rk4),
+            (
+                syn::parse_quote!(#[demo(ode_solver(algo = "rk45", rtol = "1e-3"))] struct A;),
+                Solver::Rk45 { rtol: 1e-3, atol: 1e-9 },
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(solver(input).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn tolerances_are_positive() {
+        let cases: [(syn::DeriveInput, &str); 5] = [
+            (
+                syn::parse_quote!(#[demo(ode_solver(algo = "rk45", rtol = "0"))] struct A;),
+                "rtol = \"0\" must be a positive number #Solver",
+            ),
+            (
+                syn::parse_quote!(#[demo(ode_solver(algo = "rk45", atol = "-1e-9"))] struct A;),
+                "atol = \"-1e-9\" must be a positive number #Solver",
+            ),
+            (
+                syn::parse_quote!(#[demo(ode_solver(algo = "rk45", rtol = "NaN"))] struct A;),
+                "rtol = \"NaN\" must be a positive number #Solver",
+            ),
+            (
+                syn::parse_quote!(#[demo(ode_solver(algo = "rk45", atol = "tiny"))] struct A;),
+                "atol = \"tiny\" is not a number #Solver",
+            ),
+            (
+                syn::parse_quote!(#[demo(ode_solver(algo = "rk4", rtol = "1e-6"))] struct A;),
+                "rtol only applies to rk45, not to rk4 #Solver",
+            ),
+        ];
+        for (input, msg) in cases {
+            assert_eq!(solver(input).unwrap_err().to_string(), msg);
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use std::fmt::Write;
+
+use derive_attr_parser::{Container, Val};
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+
+use crate::sim::{Model, Node};
+use crate::solver::Solver;
+
+// `Bass::XMILE`, the model as XMILE 1.0 document for system dynamics tools:
+// param -> <aux> with a constant eqn, stock -> <stock> with the initial eqn and its inflows and
+// outflows, flow -> <flow>, var -> <aux>. A flow end without stock is a cloud, XMILE has nothing
+// to write for it. The sim_specs come from the ode_solver and
+// #[demo(sim_specs(start = "0", stop = "100", dt = "1"))], these defaults without it.
+
+const SIM_SPECS: &str = "sim_specs";
+
+/// The XMILE document of the model.
+pub(crate) fn xmile(cont: &Container, model: &Model) -> syn::Result<String> {
+    let name = match cont.attr("name") {
+        Ok(Val::Str(name)) => name.clone(),
+        _ => cont.ident.to_string(),
+    };
+    let method = match model.solver {
+        Solver::Euler => "Euler",
+        Solver::Heun => "RK2",
+        Solver::Rk4 => "RK4",
+        Solver::Rk45 { .. } => "RK45",
+    };
+    let mut specs = vec![];
+    for (key, default) in [("start", "0"), ("stop", "100"), ("dt", "1")] {
+        let val = match cont.attr(&format!("{SIM_SPECS}.{key}")) {
+            Ok(Val::Str(val)) if val.parse::<f64>().is_ok() => val.as_str(),
+            Ok(_) => {
+                let span = cont
+                    .entries
+                    .iter()
+                    .find(|entry| entry.key == SIM_SPECS)
+                    .map(|entry| entry.span)
+                    .unwrap_or_else(|| cont.ident.span());
+                let msg = format!("expect {SIM_SPECS}({key} = \"number\") #xmile");
+                return Err(syn::Error::new(span, msg));
+            }
+            Err(_) => default,
+        };
+        specs.push((key, val));
+    }
+
+    let mut doc = String::new();
+    doc.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    doc.push_str("<xmile version=\"1.0\" xmlns=\"http://docs.oasis-open.org/xmile/ns/XMILE/v1.0\">\n");
+    doc.push_str("  <header>\n");
+    let _ = writeln!(doc, "    <name>{}</name>", escape(&name));
+    doc.push_str("    <vendor>derive-attr-parser</vendor>\n");
+    let version = env!("CARGO_PKG_VERSION");
+    let _ = writeln!(doc, "    <product version=\"{version}\">demo-derive</product>");
+    doc.push_str("  </header>\n");
+    let _ = writeln!(doc, "  <sim_specs method=\"{method}\">");
+    for (key, val) in specs {
+        let _ = writeln!(doc, "    <{key}>{val}</{key}>");
+    }
+    doc.push_str("  </sim_specs>\n");
+    doc.push_str("  <model>\n    <variables>\n");
+    for stock in &model.stocks {
+        let _ = writeln!(doc, "      <stock name=\"{}\">", stock.ident);
+        write_eqn(&mut doc, stock)?;
+        for flow in &model.flows {
+            if flow.to.as_ref() == Some(&stock.ident) {
+                let _ = writeln!(doc, "        <inflow>{}</inflow>", flow.node.ident);
+            }
+            if flow.from.as_ref() == Some(&stock.ident) {
+                let _ = writeln!(doc, "        <outflow>{}</outflow>", flow.node.ident);
+            }
+        }
+        doc.push_str("      </stock>\n");
+    }
+    for flow in &model.flows {
+        let _ = writeln!(doc, "      <flow name=\"{}\">", flow.node.ident);
+        write_eqn(&mut doc, &flow.node)?;
+        doc.push_str("      </flow>\n");
+    }
+    for aux in model.vars.iter().chain(&model.params) {
+        let _ = writeln!(doc, "      <aux name=\"{}\">", aux.ident);
+        write_eqn(&mut doc, aux)?;
+        doc.push_str("      </aux>\n");
+    }
+    doc.push_str("    </variables>\n  </model>\n</xmile>\n");
+    Ok(doc)
+}
+
+// A node without val starts at, or is, 0.
+fn write_eqn(doc: &mut String, node: &Node) -> syn::Result<()> {
+    let eqn = match &node.val {
+        Some(val) => equation(val).map_err(|msg| {
+            let msg = format!("{msg} in the val of `{}` #xmile", node.ident);
+            syn::Error::new(node.val_span, msg)
+        })?,
+        None => "0".to_string(),
+    };
+    let _ = writeln!(doc, "        <eqn>{}</eqn>", escape(&eqn));
+    Ok(())
+}
+
+/// `val` in XMILE syntax: no literal suffixes, builtins for the float methods and the
+/// `f64::consts`. Rust and XMILE agree on the precedence of the operators, the parens of
+/// `val` carry over, only what Rust writes without parens needs them in XMILE.
+fn equation(expr: &syn::Expr) -> Result<String, String> {
+    let eqn = match expr {
+        syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(int), .. }) => {
+            int.base10_digits().to_string()
+        }
+        syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Float(float), .. }) => {
+            float.base10_digits().to_string()
+        }
+        syn::Expr::Paren(paren) => format!("({})", equation(&paren.expr)?),
+        syn::Expr::Group(group) => equation(&group.expr)?,
+        syn::Expr::Path(path) => path_equation(path)?,
+        syn::Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr, .. }) => {
+            format!("-{}", if_operand(expr)?)
+        }
+        syn::Expr::Unary(syn::ExprUnary { op: syn::UnOp::Not(_), expr, .. }) => {
+            format!("NOT {}", if_operand(expr)?)
+        }
+        syn::Expr::Binary(binary) => match binary_op(&binary.op) {
+            Some(op) => format!("{} {op} {}", if_operand(&binary.left)?, if_operand(&binary.right)?),
+            None => return Err(format!("`{}` has no XMILE operator", binary.op.to_token_stream())),
+        },
+        syn::Expr::MethodCall(call) => {
+            // the parens of `(a + b).max(c)` are no longer needed in `MAX(a + b, c)`.
+            let arg = match &*call.receiver {
+                syn::Expr::Paren(paren) => equation(&paren.expr)?,
+                receiver => equation(receiver)?,
+            };
+            let args = call.args.iter().map(equation).collect::<Result<Vec<_>, _>>()?;
+            match (call.method.to_string().as_str(), &args[..]) {
+                // `^` binds right, `a.powf(b).powf(c)` is `(a ^ b) ^ c`.
+                ("powf" | "powi", [_]) => {
+                    let exp = &call.args[0];
+                    format!("{} ^ {}", operand(&call.receiver)?, operand(exp)?)
+                }
+                (method @ ("max" | "min"), [other]) => {
+                    format!("{}({arg}, {other})", method.to_uppercase())
+                }
+                (method @ ("abs" | "exp" | "ln" | "log10" | "sqrt" | "sin" | "cos" | "tan"), []) => {
+                    format!("{}({arg})", method.to_uppercase())
+                }
+                ("floor", []) => format!("INT({arg})"),
+                _ => return Err(format!("`{}` has no XMILE builtin", call.method)),
+            }
+        }
+        syn::Expr::If(syn::ExprIf {
+            cond,
+            then_branch,
+            else_branch: Some((_, other)),
+            ..
+        }) => match block_expr(then_branch) {
+            Some(then) => {
+                let (cond, then, other) = (equation(cond)?, equation(then)?, equation(other)?);
+                format!("IF {cond} THEN {then} ELSE {other}")
+            }
+            None => return Err("an `if` branch has more than one expression".to_string()),
+        },
+        syn::Expr::Block(syn::ExprBlock { block, .. }) => match block_expr(block) {
+            Some(inner) => equation(inner)?,
+            None => return Err("a block has more than one expression".to_string()),
+        },
+        _ => return Err(format!("`{}` has no XMILE equivalent", expr.to_token_stream())),
+    };
+    Ok(eqn)
+}
+
+// The operand of `^`: anything but a literal, name, call or parens in parens.
+fn operand(expr: &syn::Expr) -> Result<String, String> {
+    let eqn = equation(expr)?;
+    let atomic = match expr {
+        syn::Expr::Lit(_) | syn::Expr::Path(_) | syn::Expr::Paren(_) => true,
+        syn::Expr::Group(group) => return operand(&group.expr),
+        syn::Expr::MethodCall(call) => !matches!(call.method.to_string().as_str(), "powf" | "powi"),
+        _ => false,
+    };
+    Ok(if atomic { eqn } else { format!("({eqn})") })
+}
+
+// The operand of a unary or binary operator: `ELSE` takes all that follows, so an `if` in parens.
+fn if_operand(expr: &syn::Expr) -> Result<String, String> {
+    match expr {
+        syn::Expr::If(_) => Ok(format!("({})", equation(expr)?)),
+        _ => equation(expr),
+    }
+}
+
+// A field name, or one of `std::f64::consts`, `PI` alone too.
+fn path_equation(path: &syn::ExprPath) -> Result<String, String> {
+    let segments: Vec<_> = path.path.segments.iter().map(|seg| seg.ident.to_string()).collect();
+    let name = match &segments[..] {
+        [name] if !name.starts_with(char::is_uppercase) => return Ok(name.clone()),
+        [name] => name,
+        [prefix @ .., consts, name]
+            if consts == "consts"
+                && path.qself.is_none()
+                && prefix.iter().all(|seg| matches!(seg.as_str(), "std" | "core" | "f64")) =>
+        {
+            name
+        }
+        _ => return Err(format!("`{}` has no XMILE equivalent", path.to_token_stream())),
+    };
+    let val = match name.as_str() {
+        "PI" => return Ok("PI".to_string()),
+        "E" => return Ok("EXP(1)".to_string()),
+        "TAU" => std::f64::consts::TAU,
+        "SQRT_2" => std::f64::consts::SQRT_2,
+        "LN_2" => std::f64::consts::LN_2,
+        "LN_10" => std::f64::consts::LN_10,
+        "LOG2_E" => std::f64::consts::LOG2_E,
+        "LOG10_E" => std::f64::consts::LOG10_E,
+        "LOG2_10" => std::f64::consts::LOG2_10,
+        "LOG10_2" => std::f64::consts::LOG10_2,
+        "FRAC_PI_2" => std::f64::consts::FRAC_PI_2,
+        "FRAC_PI_3" => std::f64::consts::FRAC_PI_3,
+        "FRAC_PI_4" => std::f64::consts::FRAC_PI_4,
+        "FRAC_PI_6" => std::f64::consts::FRAC_PI_6,
+        "FRAC_PI_8" => std::f64::consts::FRAC_PI_8,
+        "FRAC_1_PI" => std::f64::consts::FRAC_1_PI,
+        "FRAC_2_PI" => std::f64::consts::FRAC_2_PI,
+        "FRAC_2_SQRT_PI" => std::f64::consts::FRAC_2_SQRT_PI,
+        "FRAC_1_SQRT_2" => std::f64::consts::FRAC_1_SQRT_2,
+        _ => return Err(format!("`{}` has no XMILE equivalent", path.to_token_stream())),
+    };
+    Ok(val.to_string())
+}
+
+// `{ expr }`
+fn block_expr(block: &syn::Block) -> Option<&syn::Expr> {
+    match &block.stmts[..] {
+        [syn::Stmt::Expr(expr, None)] => Some(expr),
+        _ => None,
+    }
+}
+
+fn binary_op(op: &syn::BinOp) -> Option<&'static str> {
+    let op = match op {
+        syn::BinOp::Add(_) => "+",
+        syn::BinOp::Sub(_) => "-",
+        syn::BinOp::Mul(_) => "*",
+        syn::BinOp::Div(_) => "/",
+        syn::BinOp::Rem(_) => "MOD",
+        syn::BinOp::And(_) => "AND",
+        syn::BinOp::Or(_) => "OR",
+        syn::BinOp::Eq(_) => "=",
+        syn::BinOp::Ne(_) => "<>",
+        syn::BinOp::Lt(_) => "<",
+        syn::BinOp::Le(_) => "<=",
+        syn::BinOp::Gt(_) => ">",
+        syn::BinOp::Ge(_) => ">=",
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// `const XMILE: &str` of the model.
+pub(crate) fn xmile_tokens(cont: &Container, model: &Model) -> syn::Result<TokenStream> {
+    let doc = xmile(cont, model)?;
+    Ok(cont.impl_inherent(quote! {
+        /// The model as XMILE document, for system dynamics tools.
+        pub const XMILE: &'static str = #doc;
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eqn(val: &str) -> Result<String, String> {
+        equation(&syn::parse_str(val).unwrap())
+    }
+
+    #[test]
+    fn equations() {
+        let cases = [
+            ("x.powf(1.0 / 3.0)", "x ^ (1.0 / 3.0)"),
+            ("a.powf(b + c)", "a ^ (b + c)"),
+            ("(a + b).powi(2)", "(a + b) ^ 2"),
+            ("a.powf(b).powf(c)", "(a ^ b) ^ c"),
+            ("a.powf(b.powf(c))", "a ^ (b ^ c)"),
+            ("-x.powf(2.0)", "-x ^ 2.0"),
+            ("x.powf(-1.0)", "x ^ (-1.0)"),
+            ("a.exp().powf(b.max(c))", "EXP(a) ^ MAX(b, c)"),
+            ("2.0 * std::f64::consts::PI * r", "2.0 * PI * r"),
+            ("f64::consts::E.powf(t)", "EXP(1) ^ t"),
+            ("core::f64::consts::TAU", &std::f64::consts::TAU.to_string()),
+            ("if a > b { a } else { b } + 1.0", "(IF a > b THEN a ELSE b) + 1.0"),
+        ];
+        for (val, expected) in cases {
+            assert_eq!(eqn(val).as_deref(), Ok(expected), "{val}");
+        }
+    }
+
+    #[test]
+    fn no_xmile_equivalent() {
+        let cases = [
+            ("a + f32::consts::PI", "`f32 :: consts :: PI` has no XMILE equivalent"),
+            ("a + MAX_RATE", "`MAX_RATE` has no XMILE equivalent"),
+            ("a.clamp(b, c)", "`clamp` has no XMILE builtin"),
+            ("a & b", "`&` has no XMILE operator"),
+        ];
+        for (val, msg) in cases {
+            assert_eq!(eqn(val).unwrap_err(), msg, "{val}");
+        }
+    }
+}
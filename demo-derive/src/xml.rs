@@ -0,0 +1,224 @@
+// Just enough XML for XMILE: elements, attributes, text and the five predefined entities.
+// The prolog, comments, processing instructions and DOCTYPE are skipped, CDATA is text.
+// Namespace prefixes are dropped, `<isee:spec>` reads as `spec`.
+
+/// An element with its attributes, child elements and text.
+#[derive(Debug, Default)]
+pub(crate) struct Element {
+    pub(crate) name: String,
+    pub(crate) attrs: Vec<(String, String)>,
+    pub(crate) children: Vec<Element>,
+    pub(crate) text: String,
+}
+
+impl Element {
+    pub(crate) fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.iter().find(|(key, _)| key == name).map(|(_, val)| val.as_str())
+    }
+
+    pub(crate) fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    pub(crate) fn children<'e>(&'e self, name: &'e str) -> impl Iterator<Item = &'e Element> + 'e {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// The trimmed text of the child `name`.
+    pub(crate) fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|child| child.text.trim())
+    }
+}
+
+/// The root element of `text`.
+pub(crate) fn parse(text: &str) -> Result<Element, String> {
+    let mut reader = Reader { text, at: 0 };
+    reader.skip_misc()?;
+    let root = reader.element()?;
+    reader.skip_misc()?;
+    if reader.at < text.len() {
+        return Err(reader.error("content after the root element"));
+    }
+    Ok(root)
+}
+
+struct Reader<'t> {
+    text: &'t str,
+    at: usize,
+}
+
+impl<'t> Reader<'t> {
+    fn rest(&self) -> &'t str {
+        &self.text[self.at..]
+    }
+
+    fn error(&self, msg: &str) -> String {
+        let line = self.text[..self.at].matches('\n').count() + 1;
+        format!("{msg} at line {line}")
+    }
+
+    fn skip_ws(&mut self) {
+        let rest = self.rest();
+        self.at += rest.len() - rest.trim_start().len();
+    }
+
+    // Skip to after `end`.
+    fn skip_past(&mut self, end: &str) -> Result<&'t str, String> {
+        let rest = self.rest();
+        match rest.find(end) {
+            Some(found) => {
+                self.at += found + end.len();
+                Ok(&rest[..found])
+            }
+            None => Err(self.error(&format!("missing `{end}`"))),
+        }
+    }
+
+    // Whitespace, `<?..?>`, `<!--..-->` and `<!DOCTYPE..>` between elements.
+    fn skip_misc(&mut self) -> Result<(), String> {
+        loop {
+            self.skip_ws();
+            if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<!DOCTYPE") {
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '=' | '>' | '/'))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error("expect a name"));
+        }
+        self.at += len;
+        let name = &rest[..len];
+        Ok(name.rsplit(':').next().unwrap_or(name).to_string())
+    }
+
+    fn element(&mut self) -> Result<Element, String> {
+        if !self.rest().starts_with('<') {
+            return Err(self.error("expect an element"));
+        }
+        self.at += 1;
+        let mut element = Element {
+            name: self.name()?,
+            ..Element::default()
+        };
+        loop {
+            self.skip_ws();
+            if self.rest().starts_with("/>") {
+                self.at += 2;
+                return Ok(element);
+            }
+            if self.rest().starts_with('>') {
+                self.at += 1;
+                break;
+            }
+            let key = self.name()?;
+            self.skip_ws();
+            if !self.rest().starts_with('=') {
+                return Err(self.error(&format!("expect `=` after `{key}`")));
+            }
+            self.at += 1;
+            self.skip_ws();
+            let quote = match self.rest().chars().next() {
+                Some(quote @ ('"' | '\'')) => quote,
+                _ => return Err(self.error(&format!("expect a quoted value of `{key}`"))),
+            };
+            self.at += 1;
+            let val = self.skip_past(&quote.to_string())?;
+            element.attrs.push((key, unescape(val)));
+        }
+        loop {
+            let rest = self.rest();
+            let text_len = rest.find('<').unwrap_or(rest.len());
+            element.text.push_str(&unescape(&rest[..text_len]));
+            self.at += text_len;
+            let rest = self.rest();
+            if rest.is_empty() {
+                return Err(self.error(&format!("`<{}>` is not closed", element.name)));
+            } else if rest.starts_with("</") {
+                self.at += 2;
+                let name = self.name()?;
+                if name != element.name {
+                    let msg = format!("`</{name}>` closes `<{}>`", element.name);
+                    return Err(self.error(&msg));
+                }
+                self.skip_ws();
+                self.skip_past(">")?;
+                return Ok(element);
+            } else if rest.starts_with("<![CDATA[") {
+                self.at += "<![CDATA[".len();
+                let text = self.skip_past("]]>")?;
+                element.text.push_str(text);
+            } else if rest.starts_with("<!--") {
+                // not skip_misc, the whitespace after it is text.
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else {
+                element.children.push(self.element()?);
+            }
+        }
+    }
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entities_cdata_and_comments() {
+        let root = parse(
+            r#"<?xml version="1.0"?>
+<!-- before the root -->
+<isee:model name="a &amp; b" q='&quot;x&quot;'>
+  <eqn>a &lt; b &amp;&amp; c &gt; &apos;d&apos;</eqn>
+  <!-- <eqn>skipped</eqn> -->
+  <eqn><![CDATA[a < b & <c>]]></eqn>
+  <doc>one<?pi skipped?> two<!-- skipped --> three</doc>
+  <empty/>
+</isee:model>
+<!-- after the root -->"#,
+        )
+        .unwrap();
+        assert_eq!(root.name, "model");
+        assert_eq!(root.attr("name"), Some("a & b"));
+        assert_eq!(root.attr("q"), Some("\"x\""));
+        let eqns: Vec<_> = root.children("eqn").map(|eqn| eqn.text.as_str()).collect();
+        assert_eq!(eqns, ["a < b && c > 'd'", "a < b & <c>"]);
+        assert_eq!(root.child_text("doc"), Some("one two three"));
+        assert!(root.child("empty").is_some_and(|empty| empty.children.is_empty()));
+        assert_eq!(root.children.len(), 4);
+    }
+
+    #[test]
+    fn errors() {
+        let cases = [
+            ("<a><b></a>", "`</a>` closes `<b>` at line 1"),
+            ("<a>\n<b>", "`<b>` is not closed at line 2"),
+            ("<a x=1/>", "expect a quoted value of `x` at line 1"),
+            ("<a/><b/>", "content after the root element at line 1"),
+            ("<a><![CDATA[x</a>", "missing `]]>` at line 1"),
+        ];
+        for (text, msg) in cases {
+            assert_eq!(parse(text).unwrap_err(), msg, "{text}");
+        }
+    }
+}
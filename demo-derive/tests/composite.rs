@@ -0,0 +1,28 @@
+use demo_derive::DemoDerive;
+
+// Only the attrs, no method: a part as the composite sees it through its META.
+#[derive(DemoDerive, Debug, Clone)]
+#[demo(input_name = "{Part}Input")]
+struct Part {
+    #[demo(output(to = "{total}"))]
+    total: f64,
+}
+
+// Braces in the names are part of the message, not a format string.
+#[derive(DemoDerive, Debug, Clone)]
+#[demo(method = "composited")]
+struct Whole {
+    #[demo(model(input = "{Part}Input"), output(from = "{total}", to = "total"))]
+    part: Part,
+}
+
+#[test]
+fn checks_pass_with_braces_in_names() {
+    use derive_attr_runtime::{AttrReflect, StaticVal};
+
+    let part = Whole::META.field("part").unwrap();
+    assert_eq!(part.attr("model.input"), Some(&StaticVal::Str("{Part}Input")));
+    assert_eq!(Part::META.field("total").unwrap().ty, "f64");
+    let whole = Whole { part: Part { total: 1.0 } };
+    assert_eq!(whole.part.total, 1.0);
+}
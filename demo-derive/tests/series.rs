@@ -0,0 +1,58 @@
+use demo_derive::DemoDerive;
+
+// A model generic over a field without role, `new` fills it with Default.
+#[derive(DemoDerive, Debug, Clone)]
+#[demo(method = "system_dynamics")]
+struct Decay<T: Default> {
+    #[demo(stock(val = "100.0"))]
+    level: f64,
+    #[demo(flow(from = "level", val = "level * 0.05"))]
+    decay: f64,
+    tag: T,
+}
+
+#[test]
+fn generic_model_runs() {
+    let mut model = Decay::<String>::new();
+    let series = model.run(0.0, 1.0, 1.0).unwrap();
+    assert_eq!(DecaySeries::COLUMNS, ["time", "level", "decay"]);
+    assert_eq!(series.time, [0.0, 1.0]);
+    assert_eq!(series.level, [100.0, 95.0]);
+    assert_eq!(series.decay, [5.0, 4.75]);
+    assert!(model.tag.is_empty());
+}
+
+#[test]
+fn last_step_is_shorter() {
+    let mut model = Decay::<()>::new();
+    let series = model.run(0.0, 1.0, 0.4).unwrap();
+    assert_eq!(series.len(), 4);
+    assert_eq!(series.time[..3], [0.0, 0.4, 0.8]);
+    assert!((series.time[3] - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn bad_arguments_are_none() {
+    let mut model = Decay::<()>::new();
+    for (start, stop, dt) in [
+        (0.0, 1.0, 0.0),
+        (0.0, 1.0, -0.1),
+        (1.0, 0.0, 0.1),
+        (0.0, f64::INFINITY, 0.1),
+        (f64::NAN, 1.0, 0.1),
+        (0.0, 1.0, f64::NAN),
+    ] {
+        assert!(model.run(start, stop, dt).is_none(), "run({start}, {stop}, {dt})");
+    }
+    assert_eq!(model.level, 100.0);
+}
+
+#[test]
+fn csv() {
+    let mut model = Decay::<()>::new();
+    let series = model.run(0.0, 2.0, 1.0).unwrap();
+    let mut csv = vec![];
+    series.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert_eq!(csv, "time,level,decay\n0,100,5\n1,95,4.75\n2,90.25,4.5125\n");
+}
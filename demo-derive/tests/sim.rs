@@ -0,0 +1,102 @@
+use demo_derive::DemoDerive;
+
+// Fields named like the bindings of the expansion: the `state` of `derivatives`, the `input` of
+// `from_input`, the `model` of `new` and the `dt`, `y` and `h` of the rk45 step.
+#[derive(DemoDerive, Debug, Clone)]
+#[demo(method = "system_dynamics", ode_solver = "rk45")]
+struct Names {
+    #[demo(param(val = "0.5"), input(from = "input"))]
+    input: f64,
+    #[demo(param(val = "0.1"))]
+    dt: f64,
+    #[demo(stock(val = "1.0"))]
+    state: f64,
+    #[demo(stock(val = "2.0"))]
+    model: f64,
+    #[demo(var(val = "state * input + model * dt"))]
+    y: f64,
+    #[demo(flow(from = "state", val = "state * input"))]
+    h: f64,
+    #[demo(flow(to = "model", val = "y"))]
+    inflow: f64,
+}
+
+#[test]
+fn field_names_do_not_shadow_the_expansion() {
+    let model = Names::from_input(&NamesInput { input: 0.25 });
+    assert_eq!(model.input, 0.25);
+    assert_eq!(model.derivatives(&[1.0, 2.0]), [-0.25, 0.25 + 0.2]);
+    let mut model = Names::new();
+    assert_eq!(model.y, 0.5 + 0.2);
+    model.step(1.0);
+    assert!((model.state - (-0.5_f64).exp()).abs() < 1e-6);
+}
+
+// The roles from a TOML include, with numbers for val.
+#[derive(DemoDerive, Debug, Clone)]
+#[demo(include = "tests/models/decay.toml")]
+struct Included {
+    rate: f64,
+    level: f64,
+    decay: f64,
+}
+
+#[test]
+fn numbers_from_an_include() {
+    let model = Included::new();
+    assert_eq!((model.rate, model.level, model.decay), (0.5, 2.0, 1.0));
+}
+
+#[test]
+fn attrs_reflect_at_runtime() {
+    use derive_attr_runtime::{AttrReflect, StaticVal};
+
+    let meta = Names::META;
+    assert_eq!(meta.ident, "Names");
+    assert_eq!(meta.attr("ode_solver"), Some(&StaticVal::Str("rk45")));
+    let stocks: Vec<_> = meta.fields_with("stock").map(|field| field.name).collect();
+    assert_eq!(stocks, ["state", "model"]);
+    let h = meta.field("h").unwrap();
+    assert_eq!(h.ty, "f64");
+    assert_eq!(h.attr("flow.from").and_then(StaticVal::as_str), Some("state"));
+    // the numbers of an include stay numbers.
+    assert_eq!(Included::META.field("rate").unwrap().attr("param.val"), Some(&StaticVal::Float(0.5)));
+}
+
+// `sim` is an alias of `demo`, both may mix.
+#[derive(DemoDerive, Debug, Clone)]
+#[sim(method = "system_dynamics")]
+struct Aliased {
+    #[sim(stock(val = "4.0"))]
+    level: f64,
+    #[demo(flow(from = "level", val = "level * 0.5"))]
+    drain: f64,
+}
+
+#[test]
+fn sim_is_read_as_demo() {
+    let model = Aliased::new();
+    assert_eq!((model.level, model.drain), (4.0, 2.0));
+}
+
+// a param and a stock set by the input, two outputs of one field.
+#[derive(DemoDerive, Debug, Clone)]
+#[demo(method = "system_dynamics", output_name = "TankOutput")]
+struct Tank {
+    #[demo(param(val = "1.0"), input(from = "size"))]
+    size: f64,
+    #[demo(stock(val = "size * 2.0"), input(from = "level"), output(to = "level"), output(to = "volume"))]
+    level: f64,
+    #[demo(flow(from = "level", val = "1.0"), output(to = "drain"))]
+    drain: f64,
+}
+
+#[test]
+fn input_overrides_and_output_follows_the_steps() {
+    let model = Tank::from_input(&TankInput { size: 3.0, level: 4.0 });
+    assert_eq!((model.size, model.level), (3.0, 4.0));
+    assert_eq!(Tank::new().level, 2.0);
+    let mut model = model;
+    model.step(1.0);
+    assert_eq!(model.output(), TankOutput { level: 3.0, volume: 3.0, drain: 1.0 });
+}
@@ -0,0 +1,82 @@
+use demo_derive::DemoDerive;
+
+// d level / dt = -k * level, level(t) = exp(-k * t).
+macro_rules! decay {
+    ($ident:ident, $($solver:tt)*) => {
+        #[derive(DemoDerive, Debug, Clone)]
+        #[demo(method = "system_dynamics", $($solver)*)]
+        struct $ident {
+            #[demo(param(val = "0.5"))]
+            k: f64,
+            #[demo(stock(val = "1.0"))]
+            level: f64,
+            #[demo(flow(from = "level", val = "k * level"))]
+            decay: f64,
+        }
+    };
+}
+
+decay!(DecayEuler, ode_solver = "euler");
+decay!(DecayHeun, ode_solver = "heun");
+decay!(DecayRk4, ode_solver = "rk4");
+decay!(DecayRk45, ode_solver(algo = "rk45", rtol = "1e-10", atol = "1e-12"));
+
+const STOP: f64 = 2.0;
+
+fn exact() -> f64 {
+    (-0.5 * STOP).exp()
+}
+
+// The error at STOP in steps of dt.
+macro_rules! error {
+    ($ident:ident, $dt:expr) => {{
+        let mut model = $ident::new();
+        let steps = (STOP / $dt).round() as usize;
+        for _ in 0..steps {
+            model.step($dt);
+        }
+        (model.level - exact()).abs()
+    }};
+}
+
+// Halving dt divides the error of a method of order p by about 2^p.
+macro_rules! assert_order {
+    ($ident:ident, $order:expr) => {{
+        let ratio = error!($ident, 0.1) / error!($ident, 0.05);
+        let expected = 2_f64.powi($order);
+        assert!(
+            (ratio / expected - 1.0).abs() < 0.1,
+            "{}: error ratio {ratio}, expect about {expected}",
+            stringify!($ident)
+        );
+    }};
+}
+
+#[test]
+fn euler_is_first_order() {
+    assert!(error!(DecayEuler, 0.1) < 1e-2);
+    assert_order!(DecayEuler, 1);
+}
+
+#[test]
+fn heun_is_second_order() {
+    assert!(error!(DecayHeun, 0.1) < 2e-4);
+    assert_order!(DecayHeun, 2);
+}
+
+#[test]
+fn rk4_is_fourth_order() {
+    assert!(error!(DecayRk4, 0.1) < 1e-7);
+    assert_order!(DecayRk4, 4);
+}
+
+#[test]
+fn rk45_meets_the_tolerance() {
+    // one call covers the whole span, the step size control splits it.
+    let mut model = DecayRk45::new();
+    model.step(STOP);
+    assert!((model.level - exact()).abs() < 1e-9, "{} vs {}", model.level, exact());
+    // backwards in time too.
+    model.step(-STOP);
+    assert!((model.level - 1.0).abs() < 1e-9, "{}", model.level);
+}
@@ -0,0 +1,60 @@
+use demo_derive::{sim_model, DemoDerive};
+
+// Newton cooling with a power law and a heater, exported as XMILE and imported back.
+#[derive(DemoDerive, Debug, Clone)]
+#[demo(
+    name = "Cooling",
+    method = "system_dynamics",
+    ode_solver = "rk4",
+    sim_specs(start = "0", stop = "10", dt = "0.25")
+)]
+struct Cooling {
+    #[demo(param(val = "20.0"))]
+    room: f64,
+    #[demo(param(val = "0.3"))]
+    k: f64,
+    #[demo(param(val = "1.5"))]
+    n: f64,
+    #[demo(param(val = "2.0"))]
+    heater: f64,
+    #[demo(var(val = "temperature - room"))]
+    gap: f64,
+    #[demo(var(val = "k * gap.abs().powf(1.0 / n) * if gap > 0.0 { 1.0 } else { -1.0 }"))]
+    rate: f64,
+    #[demo(stock(val = "90.0"))]
+    temperature: f64,
+    #[demo(flow(from = "temperature", val = "rate - heater * (std::f64::consts::PI / 6.0).sin()"))]
+    cooling: f64,
+}
+
+// tests/models/cooling.xmile is Cooling::XMILE, checked in for sim_model! to read.
+sim_model!(Imported = "tests/models/cooling.xmile");
+
+#[test]
+fn fixture_is_the_export() {
+    // the product version moves with the crate, the fixture keeps the one it was written with.
+    let product = |xmile: &str| {
+        let line = xmile.lines().find(|line| line.trim_start().starts_with("<product "));
+        line.unwrap().to_string()
+    };
+    let (export, fixture) = (Cooling::XMILE, include_str!("models/cooling.xmile"));
+    let version = format!("<product version=\"{}\">demo-derive</product>", env!("CARGO_PKG_VERSION"));
+    assert_eq!(product(export).trim(), version);
+    assert_eq!(export.replace(&product(export), ""), fixture.replace(&product(fixture), ""));
+}
+
+#[test]
+fn import_runs_the_same() {
+    let series = Cooling::new().run(0.0, 10.0, 0.25).unwrap();
+    let imported = Imported::new().run(0.0, 10.0, 0.25).unwrap();
+    assert_eq!(series.len(), imported.len());
+    for (at, (a, b)) in series.temperature.iter().zip(&imported.temperature).enumerate() {
+        assert!((a - b).abs() < 1e-12, "temperature at {at}: {a} != {b}");
+    }
+    assert!(series.temperature[series.len() - 1] < 90.0);
+}
+
+#[test]
+fn export_of_the_import_is_the_same() {
+    assert_eq!(Imported::XMILE, Cooling::XMILE);
+}
@@ -1,3 +1,14 @@
 pub mod ast;
+pub mod codegen;
 pub mod ctxt;
+pub mod foreign;
+pub mod include;
+pub mod literal;
 pub mod parse;
+pub mod query;
+pub mod reflect;
+pub mod template;
+pub mod ty;
+pub mod visit;
+pub mod visit_mut;
+pub mod fold;
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::fmt::{self, Display};
+use std::path::PathBuf;
 use proc_macro2::Span;
 use syn::{Expr, Ident, Path};
 
@@ -13,6 +14,17 @@ pub struct Container<'a> {
     pub ident: syn::Ident,
     /// Attributes on the structure.
     pub attrs: HashMap<String, Val>,
+    /// The `cfg_attr` predicate of the keys in `attrs` came from `#[cfg_attr(pred, root(..))]`.
+    /// Empty for a derive input, see `Options::cfg_attr`.
+    pub cfgs: HashMap<String, syn::Meta>,
+    /// The `///` doc comments, lines joined with `\n`.
+    pub doc: Option<String>,
+    /// Every key of the root attrs in source order, `attrs` is the merge of them.
+    pub entries: Vec<AttrEntry>,
+    /// The files loaded by `Options::include_key`.
+    pub includes: Vec<PathBuf>,
+    /// The env vars `{env:VAR}` read with `Options::interpolate`.
+    pub envs: Vec<String>,
     /// The contents of the struct or enum.
     pub data: Data<'a>,
     /// Any generics on the struct or enum.
@@ -47,6 +59,11 @@ pub enum Style {
 pub struct Variant<'a> {
     pub ident: syn::Ident,
     pub attrs: HashMap<String, Val>,
+    pub cfgs: HashMap<String, syn::Meta>,
+    pub doc: Option<String>,
+    pub entries: Vec<AttrEntry>,
+    /// The `Options::inherit` keys of the container.
+    pub inherited: HashMap<String, Inherited>,
     pub style: Style,
     pub fields: Vec<Field<'a>>,
     pub original: &'a syn::Variant,
@@ -57,10 +74,46 @@ pub struct Variant<'a> {
 pub struct Field<'a> {
     pub member: syn::Member,
     pub attrs: HashMap<String, Val>,
+    pub cfgs: HashMap<String, syn::Meta>,
+    pub doc: Option<String>,
+    pub entries: Vec<AttrEntry>,
+    /// The `Options::inherit` keys of the variant or container, the nearest wins.
+    pub inherited: HashMap<String, Inherited>,
     pub ty: &'a syn::Type,
     pub original: &'a syn::Field,
 }
 
+/// One key of a root attr as written, before merging into `attrs`.
+///
+/// `#[fsm(trans(to = "B"))] #[fsm(rotate, trans(to = "C"))]` has the entries
+/// trans(0), rotate(1), trans(1), while `attrs` only keeps {"trans": Val::Vec(..), "rotate": ..}.
+#[derive(Debug, Clone)]
+pub struct AttrEntry {
+    /// The top level key, eg. `trans`.
+    pub key: String,
+    /// The val of this occurrence only.
+    pub val: Val,
+    /// The span of the key.
+    pub span: Span,
+    /// The index of the attribute in `original.attrs`.
+    pub attr_index: usize,
+}
+
+/// The level an attr is declared on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttrLevel {
+    Container,
+    Variant,
+    Field,
+}
+
+/// A val inherited from an enclosing level, see `Options::inherit`.
+#[derive(Debug, Clone)]
+pub struct Inherited {
+    pub level: AttrLevel,
+    pub val: Val,
+}
+
 /// The root of helper attr, eg #[root(...)]
 /// ```rust
 /// use derive_attr_parser::Symbol;
@@ -87,7 +140,7 @@ impl PartialEq<Symbol> for Ident {
     }
 }
 
-impl<'a> PartialEq<Symbol> for &'a Ident {
+impl PartialEq<Symbol> for &Ident {
     fn eq(&self, word: &Symbol) -> bool {
         *self == word.0
     }
@@ -99,13 +152,13 @@ impl PartialEq<Symbol> for Path {
     }
 }
 
-impl<'a> PartialEq<Symbol> for &'a Path {
+impl PartialEq<Symbol> for &Path {
     fn eq(&self, word: &Symbol) -> bool {
         self.is_ident(word.0)
     }
 }
 
-impl<'a> PartialEq<Symbol> for &'a String {
+impl PartialEq<Symbol> for &String {
     fn eq(&self, other: &Symbol) -> bool {
         self.as_str() == other.0
     }
@@ -124,8 +177,13 @@ impl Display for Symbol {
 pub enum Val {
     Empty,
     Str(String),
+    /// Only from embedded literals and includes, see `Options::embedded` and
+    /// `Options::include_key`, the attr syntax itself gives strings.
+    Bool(bool),
+    Int(i64),
+    Float(f64),
     Map(HashMap<String, Val>),
-    Vec(Vec<Val>)
+    Vec(Vec<Val>),
 }
 
 impl Val {
@@ -136,11 +194,11 @@ impl Val {
                 match idr {
                     Ok(ident) => { Ok(ident) }
                     Err(err) => {
-                        Err(syn::Error::new(err.span(), format!("{} #Val.as_indent val={}", err.to_string(), s)))
+                        Err(syn::Error::new(err.span(), format!("{} #Val.as_indent val={}", err, s)))
                     }
                 }
             }
-            _ => { Err(syn::Error::new(Span::call_site(), format!("None Val::Str cannot convert to syn::Ident "))) }
+            _ => { Err(syn::Error::new(Span::call_site(), "None Val::Str cannot convert to syn::Ident ")) }
         }
     }
     pub fn as_bin_expr(&self) -> syn::Result<syn::ExprBinary> {
@@ -152,12 +210,12 @@ impl Val {
                         Ok(bin)
                     }
                     _ => {
-                        let err = syn::Error::new(Span::call_site(), format!("Only Binary Expr (eg. a+b, a*b+c) is supported"));
+                        let err = syn::Error::new(Span::call_site(), "Only Binary Expr (eg. a+b, a*b+c) is supported");
                         Err(err)
                     }
                 }
             }
-            _ => { Err(syn::Error::new(Span::call_site(), format!("None Val::Str cannot convert to syn::ExprBinary "))) }
+            _ => { Err(syn::Error::new(Span::call_site(), "None Val::Str cannot convert to syn::ExprBinary ")) }
         }
     }
     pub fn as_expr(&self) -> syn::Result<syn::Expr> {
@@ -167,11 +225,11 @@ impl Val {
                 match ret {
                     Ok(expr) => { Ok(expr) }
                     Err(err) => {
-                        Err(syn::Error::new(err.span(), format!("{} #Val.as_expr val={}", err.to_string(), s)))
+                        Err(syn::Error::new(err.span(), format!("{} #Val.as_expr val={}", err, s)))
                     }
                 }
             }
-            _ => { Err(syn::Error::new(Span::call_site(), format!("None Val::Str cannot convert to syn::Expr "))) }
+            _ => { Err(syn::Error::new(Span::call_site(), "None Val::Str cannot convert to syn::Expr ")) }
         }
     }
 }
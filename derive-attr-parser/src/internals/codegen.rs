@@ -0,0 +1,171 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::internals::ast::{Container, Val};
+
+const CRATE: &str = "crate";
+
+impl<'a> Container<'a> {
+    /// `impl #trait_path for #ident` with the generics and where clause of the input, every type
+    /// param gets `extra_bounds` as well, eg. `T: Clone` for `impl Clone`.
+    pub fn impl_trait(
+        &self,
+        trait_path: &syn::Path,
+        extra_bounds: &[syn::TypeParamBound],
+        body: TokenStream,
+    ) -> TokenStream {
+        let ident = &self.ident;
+        let generics = self.bounded_generics(extra_bounds);
+        let (impl_g, ty_g, where_c) = generics.split_for_impl();
+        quote! {
+            #[automatically_derived]
+            impl #impl_g #trait_path for #ident #ty_g #where_c {
+                #body
+            }
+        }
+    }
+
+    /// `impl #ident` with the generics and where clause of the input.
+    pub fn impl_inherent(&self, body: TokenStream) -> TokenStream {
+        let ident = &self.ident;
+        let (impl_g, ty_g, where_c) = self.generics.split_for_impl();
+        quote! {
+            #[automatically_derived]
+            impl #impl_g #ident #ty_g #where_c {
+                #body
+            }
+        }
+    }
+
+    /// The path of a runtime crate, `#[root(crate = "my_crate::runtime")]` overrides `default`
+    /// for users who re-export it.
+    ///
+    /// `cont.crate_path("::derive_attr_runtime")`
+    pub fn crate_path(&self, default: &str) -> syn::Result<syn::Path> {
+        match self.attrs.get(CRATE) {
+            Some(Val::Str(path)) => syn::parse_str(path).map_err(|err| {
+                let span = self
+                    .entries
+                    .iter()
+                    .find(|entry| entry.key == CRATE)
+                    .map_or(self.ident.span(), |entry| entry.span);
+                syn::Error::new(span, format!("{err} #Container.crate_path val={path}"))
+            }),
+            Some(_) => Err(syn::Error::new(
+                self.ident.span(),
+                "expect crate = \"path\" #Container.crate_path",
+            )),
+            None => syn::parse_str(default),
+        }
+    }
+
+    fn bounded_generics(&self, extra_bounds: &[syn::TypeParamBound]) -> syn::Generics {
+        let mut generics = self.generics.clone();
+        if extra_bounds.is_empty() {
+            return generics;
+        }
+        let params: Vec<_> = generics
+            .type_params()
+            .map(|param| param.ident.clone())
+            .collect();
+        let where_c = generics.make_where_clause();
+        for param in params {
+            where_c
+                .predicates
+                .push(syn::parse_quote!(#param: #(#extra_bounds)+*));
+        }
+        generics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::ToTokens;
+
+    use crate::internals::ast::Symbol;
+    use crate::internals::ctxt::Ctxt;
+    use crate::internals::parse::from_ast;
+
+    use super::*;
+
+    fn with_container(input: &syn::DeriveInput, f: impl FnOnce(&Container)) {
+        let cx = Ctxt::new();
+        let cont = from_ast(&cx, input, Symbol("demo")).unwrap();
+        cx.check().unwrap();
+        f(&cont);
+    }
+
+    #[test]
+    fn impls_keep_the_generics() {
+        let input = syn::parse_quote! {
+            struct Bass<'a, T: Copy, const N: usize> where T: Default {
+                clients: &'a [T; N],
+            }
+        };
+        with_container(&input, |cont| {
+            let bounds = [syn::parse_quote!(Clone), syn::parse_quote!(Send)];
+            let tokens = cont.impl_trait(&syn::parse_quote!(rt::Trait), &bounds, quote!(const A: u8 = 1;));
+            let expected = quote! {
+                #[automatically_derived]
+                impl<'a, T: Copy, const N: usize> rt::Trait for Bass<'a, T, N>
+                where
+                    T: Default,
+                    T: Clone + Send
+                {
+                    const A: u8 = 1;
+                }
+            };
+            assert_eq!(tokens.to_string(), expected.to_string());
+
+            let tokens = cont.impl_trait(&syn::parse_quote!(Trait), &[], quote!());
+            let expected = quote! {
+                #[automatically_derived]
+                impl<'a, T: Copy, const N: usize> Trait for Bass<'a, T, N> where T: Default {}
+            };
+            assert_eq!(tokens.to_string(), expected.to_string());
+
+            let tokens = cont.impl_inherent(quote!(fn f() {}));
+            let expected = quote! {
+                #[automatically_derived]
+                impl<'a, T: Copy, const N: usize> Bass<'a, T, N> where T: Default {
+                    fn f() {}
+                }
+            };
+            assert_eq!(tokens.to_string(), expected.to_string());
+        });
+    }
+
+    #[test]
+    fn crate_path_override() {
+        let path = |input: syn::DeriveInput| {
+            let mut path = None;
+            with_container(&input, |cont| {
+                path = Some(
+                    cont.crate_path("::derive_attr_runtime")
+                        .map(|path| path.to_token_stream().to_string())
+                        .map_err(|err| err.to_string()),
+                );
+            });
+            path.unwrap()
+        };
+        assert_eq!(path(syn::parse_quote!(struct Bass;)).unwrap(), ":: derive_attr_runtime");
+        let input = syn::parse_quote! {
+            #[demo(crate = "my_crate::runtime")]
+            struct Bass;
+        };
+        assert_eq!(path(input).unwrap(), "my_crate :: runtime");
+        let input = syn::parse_quote! {
+            #[demo(crate = "my crate")]
+            struct Bass;
+        };
+        assert_eq!(
+            path(input).unwrap_err(),
+            "unexpected token #Container.crate_path val=my crate"
+        );
+        let input = syn::parse_quote! {
+            #[demo(crate)]
+            struct Bass;
+        };
+        assert_eq!(path(input).unwrap_err(), "expect crate = \"path\" #Container.crate_path");
+    }
+}
@@ -0,0 +1,199 @@
+//! Owned rewrite of a parsed [`Container`], modeled on `syn::fold`.
+//!
+//! [`Fold::fold_attr`] may rename keys, e.g. normalize `ode-solver` into `ode_solver`. The keys of
+//! a map fold in byte order, of keys folded into the same name the greatest wins: `ode_solver`
+//! over `ode-solver`. The `inherited` vals of variants and fields fold the same way through
+//! `fold_attr`, `cfgs` and `entries` are passed through untouched.
+//!
+//! ```
+//! use derive_attr_parser::fold::{self, Fold};
+//! use derive_attr_parser::Val;
+//!
+//! struct Lowercase;
+//!
+//! impl Fold for Lowercase {
+//!     fn fold_attr(&mut self, key: String, val: Val) -> (String, Val) {
+//!         fold::fold_attr(self, key.to_lowercase(), val)
+//!     }
+//! }
+//! ```
+use std::collections::HashMap;
+
+use crate::internals::ast::{Container, Data, Field, Inherited, Val, Variant};
+
+pub trait Fold {
+    fn fold_container<'a>(&mut self, node: Container<'a>) -> Container<'a> {
+        fold_container(self, node)
+    }
+    fn fold_data<'a>(&mut self, node: Data<'a>) -> Data<'a> {
+        fold_data(self, node)
+    }
+    fn fold_variant<'a>(&mut self, node: Variant<'a>) -> Variant<'a> {
+        fold_variant(self, node)
+    }
+    fn fold_field<'a>(&mut self, node: Field<'a>) -> Field<'a> {
+        fold_field(self, node)
+    }
+    fn fold_attrs(&mut self, node: HashMap<String, Val>) -> HashMap<String, Val> {
+        fold_attrs(self, node)
+    }
+    fn fold_attr(&mut self, key: String, val: Val) -> (String, Val) {
+        fold_attr(self, key, val)
+    }
+    fn fold_val(&mut self, node: Val) -> Val {
+        fold_val(self, node)
+    }
+}
+
+pub fn fold_container<'a, F: Fold + ?Sized>(f: &mut F, node: Container<'a>) -> Container<'a> {
+    Container {
+        attrs: f.fold_attrs(node.attrs),
+        data: f.fold_data(node.data),
+        ..node
+    }
+}
+
+pub fn fold_data<'a, F: Fold + ?Sized>(f: &mut F, node: Data<'a>) -> Data<'a> {
+    match node {
+        Data::Enum(variants) => Data::Enum(
+            variants
+                .into_iter()
+                .map(|variant| f.fold_variant(variant))
+                .collect(),
+        ),
+        Data::Struct(style, fields) => Data::Struct(
+            style,
+            fields.into_iter().map(|field| f.fold_field(field)).collect(),
+        ),
+    }
+}
+
+pub fn fold_variant<'a, F: Fold + ?Sized>(f: &mut F, node: Variant<'a>) -> Variant<'a> {
+    Variant {
+        attrs: f.fold_attrs(node.attrs),
+        inherited: fold_inherited(f, node.inherited),
+        fields: node
+            .fields
+            .into_iter()
+            .map(|field| f.fold_field(field))
+            .collect(),
+        ..node
+    }
+}
+
+pub fn fold_field<'a, F: Fold + ?Sized>(f: &mut F, node: Field<'a>) -> Field<'a> {
+    Field {
+        attrs: f.fold_attrs(node.attrs),
+        inherited: fold_inherited(f, node.inherited),
+        ..node
+    }
+}
+
+pub fn fold_attrs<F: Fold + ?Sized>(f: &mut F, node: HashMap<String, Val>) -> HashMap<String, Val> {
+    sorted(node)
+        .into_iter()
+        .map(|(key, val)| f.fold_attr(key, val))
+        .collect()
+}
+
+// the keys an ancestor passed down, renamed as the ancestor's own.
+fn fold_inherited<F: Fold + ?Sized>(
+    f: &mut F,
+    node: HashMap<String, Inherited>,
+) -> HashMap<String, Inherited> {
+    sorted(node)
+        .into_iter()
+        .map(|(key, Inherited { level, val })| {
+            let (key, val) = f.fold_attr(key, val);
+            (key, Inherited { level, val })
+        })
+        .collect()
+}
+
+// A HashMap iterates in a different order per process, the fold must not.
+fn sorted<T>(node: HashMap<String, T>) -> Vec<(String, T)> {
+    let mut entries: Vec<_> = node.into_iter().collect();
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    entries
+}
+
+pub fn fold_attr<F: Fold + ?Sized>(f: &mut F, key: String, val: Val) -> (String, Val) {
+    (key, f.fold_val(val))
+}
+
+pub fn fold_val<F: Fold + ?Sized>(f: &mut F, node: Val) -> Val {
+    match node {
+        Val::Empty | Val::Str(_) | Val::Bool(_) | Val::Int(_) | Val::Float(_) => node,
+        Val::Map(map) => Val::Map(f.fold_attrs(map)),
+        Val::Vec(vs) => Val::Vec(vs.into_iter().map(|val| f.fold_val(val)).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::internals::ast::{AttrLevel, Symbol};
+    use crate::internals::ctxt::Ctxt;
+    use crate::internals::parse::{from_ast_with, Options};
+
+    use super::*;
+
+    struct Snake;
+
+    impl Fold for Snake {
+        fn fold_attr(&mut self, key: String, val: Val) -> (String, Val) {
+            fold_attr(self, key.replace('-', "_"), val)
+        }
+    }
+
+    #[test]
+    fn folded_keys_collide_in_key_order() {
+        for _ in 0..16 {
+            let attrs = HashMap::from([
+                ("ode_solver".to_string(), Val::Str("rk4".to_string())),
+                ("ode-solver".to_string(), Val::Str("euler".to_string())),
+                ("a-b".to_string(), Val::Int(1)),
+            ]);
+            let folded = Snake.fold_attrs(attrs);
+            assert_eq!(folded.len(), 2);
+            assert!(matches!(&folded["ode_solver"], Val::Str(solver) if solver == "rk4"));
+            assert!(matches!(folded["a_b"], Val::Int(1)));
+        }
+    }
+
+    struct Lowercase;
+
+    impl Fold for Lowercase {
+        fn fold_attr(&mut self, key: String, val: Val) -> (String, Val) {
+            fold_attr(self, key.to_lowercase(), val)
+        }
+    }
+
+    #[test]
+    fn renames_at_every_level() {
+        let input: syn::DeriveInput = syn::parse_quote! {
+            #[sim(ODE_Solver = "rk4", Algo(Step_Size = "0.1"))]
+            struct Bass {
+                #[sim(Flow(From = "a"), Trans = "x", Trans = "y")]
+                sales: f64,
+            }
+        };
+        let opts = Options {
+            inherit: vec![Symbol("ODE_Solver")],
+            ..Options::default()
+        };
+        let cx = Ctxt::new();
+        let cont = from_ast_with(&cx, &input, Symbol("sim"), &opts).unwrap();
+        cx.check().unwrap();
+
+        let cont = Lowercase.fold_container(cont);
+        assert!(matches!(cont.attr("ode_solver").unwrap(), Val::Str(s) if s == "rk4"));
+        assert!(matches!(cont.attr("algo.step_size").unwrap(), Val::Str(s) if s == "0.1"));
+        let sales = cont.field("sales").unwrap();
+        assert!(matches!(sales.attr("flow.from").unwrap(), Val::Str(s) if s == "a"));
+        assert_eq!(sales.attr("trans").unwrap().as_slice().len(), 2);
+        assert_eq!(sales.inherited["ode_solver"].level, AttrLevel::Container);
+        assert!(!sales.inherited.contains_key("ODE_Solver"));
+        // entries pass through.
+        assert_eq!(cont.entries[0].key, "ODE_Solver");
+    }
+}
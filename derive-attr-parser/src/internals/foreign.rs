@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use syn::punctuated::Punctuated;
+use syn::{Attribute, Token};
+
+use crate::internals::ast::{Container, Field, Symbol, Val, Variant};
+use crate::internals::ctxt::Ctxt;
+use crate::internals::parse::{parse_attrs, Options};
+
+// Attributes owned by other derives, parsed on demand.
+// #[serde(rename = "clients")] parse into {"rename": Val::Str("clients")}
+// #[repr(u8)] and #[derive(..)] are not key-value style, read them with parse_repr, parse_derives.
+
+/// Parse the attrs with the path `path` the same way as the root symbol, eg. `Symbol("serde")`.
+pub fn parse_foreign(cx: &Ctxt, attrs: &[Attribute], path: Symbol) -> HashMap<String, Val> {
+    match parse_attrs(cx, attrs, path, &Options::default()) {
+        Ok(parsed) => parsed.attrs,
+        Err(err) => {
+            cx.syn_error(err);
+            HashMap::new()
+        }
+    }
+}
+
+/// The `#[repr(...)]` of a struct or enum.
+#[derive(Debug, Clone, Default)]
+pub struct Repr {
+    /// `u8`, `i32`, `usize` ...
+    pub int: Option<syn::Ident>,
+    /// `C`
+    pub c: bool,
+    /// `transparent`
+    pub transparent: bool,
+    /// `packed` is `Some(1)`, `packed(N)` is `Some(N)`.
+    pub packed: Option<u64>,
+    /// `align(N)`
+    pub align: Option<u64>,
+}
+
+const REPR: Symbol = Symbol("repr");
+const DERIVE: Symbol = Symbol("derive");
+const INTS: [&str; 12] = [
+    "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+];
+
+/// Read all `#[repr(...)]` attrs into one [`Repr`].
+pub fn parse_repr(attrs: &[Attribute]) -> syn::Result<Repr> {
+    let mut repr = Repr::default();
+    for attr in attrs {
+        if attr.path() != REPR {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            let path = &meta.path;
+            if path.is_ident("C") {
+                repr.c = true;
+            } else if path.is_ident("transparent") {
+                repr.transparent = true;
+            } else if path.is_ident("packed") {
+                repr.packed = Some(if meta.input.peek(syn::token::Paren) {
+                    parse_int_arg(&meta)?
+                } else {
+                    1
+                });
+            } else if path.is_ident("align") {
+                repr.align = Some(parse_int_arg(&meta)?);
+            } else if INTS.iter().any(|int| path.is_ident(int)) {
+                repr.int = path.get_ident().cloned();
+            } else {
+                return Err(meta.error("unknown repr #parse_repr"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(repr)
+}
+
+fn parse_int_arg(meta: &syn::meta::ParseNestedMeta) -> syn::Result<u64> {
+    let content;
+    syn::parenthesized!(content in meta.input);
+    let lit: syn::LitInt = content.parse()?;
+    lit.base10_parse()
+}
+
+/// Read the paths of all `#[derive(...)]` attrs, eg. `Debug`, `serde::Serialize`.
+pub fn parse_derives(attrs: &[Attribute]) -> syn::Result<Vec<syn::Path>> {
+    let mut derives = vec![];
+    for attr in attrs {
+        if attr.path() != DERIVE {
+            continue;
+        }
+        let paths = attr.parse_args_with(Punctuated::<syn::Path, Token![,]>::parse_terminated)?;
+        derives.extend(paths);
+    }
+    Ok(derives)
+}
+
+impl<'a> Container<'a> {
+    /// See [`parse_foreign`].
+    pub fn foreign(&self, cx: &Ctxt, path: Symbol) -> HashMap<String, Val> {
+        parse_foreign(cx, &self.original.attrs, path)
+    }
+
+    /// See [`parse_repr`].
+    pub fn repr(&self) -> syn::Result<Repr> {
+        parse_repr(&self.original.attrs)
+    }
+
+    /// See [`parse_derives`].
+    pub fn derives(&self) -> syn::Result<Vec<syn::Path>> {
+        parse_derives(&self.original.attrs)
+    }
+
+    /// Whether `#[derive(..)]` lists `name`, compared by the last segment.
+    pub fn derives_trait(&self, name: &str) -> syn::Result<bool> {
+        Ok(self
+            .derives()?
+            .iter()
+            .any(|path| path.segments.last().is_some_and(|seg| seg.ident == name)))
+    }
+}
+
+impl<'a> Variant<'a> {
+    /// See [`parse_foreign`].
+    pub fn foreign(&self, cx: &Ctxt, path: Symbol) -> HashMap<String, Val> {
+        parse_foreign(cx, &self.original.attrs, path)
+    }
+}
+
+impl<'a> Field<'a> {
+    /// See [`parse_foreign`].
+    pub fn foreign(&self, cx: &Ctxt, path: Symbol) -> HashMap<String, Val> {
+        parse_foreign(cx, &self.original.attrs, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::ToTokens;
+
+    use crate::internals::parse::from_ast;
+
+    use super::*;
+
+    #[test]
+    fn serde_attrs_beside_the_root() {
+        let input: syn::DeriveInput = syn::parse_quote! {
+            #[derive(Debug, Clone, serde::Serialize)]
+            #[serde(rename_all = "camelCase")]
+            #[sim(name = "bass")]
+            struct Bass {
+                #[serde(rename = "people", default)]
+                #[sim(stock)]
+                clients: f64,
+            }
+        };
+        let cx = Ctxt::new();
+        let cont = from_ast(&cx, &input, Symbol("sim")).unwrap();
+        let serde = cont.foreign(&cx, Symbol("serde"));
+        let clients = cont.field("clients").unwrap().foreign(&cx, Symbol("serde"));
+        cx.check().unwrap();
+
+        assert!(matches!(&serde["rename_all"], Val::Str(s) if s == "camelCase"));
+        assert!(!serde.contains_key("name"));
+        assert!(matches!(&clients["rename"], Val::Str(s) if s == "people"));
+        assert!(matches!(clients["default"], Val::Empty));
+        // the root parse never sees them.
+        assert!(!cont.attrs.contains_key("rename_all"));
+
+        let derives = cont.derives().unwrap();
+        let derives: Vec<_> = derives.iter().map(|path| path.to_token_stream().to_string()).collect();
+        assert_eq!(derives, ["Debug", "Clone", "serde :: Serialize"]);
+        assert!(cont.derives_trait("Serialize").unwrap());
+        assert!(!cont.derives_trait("Default").unwrap());
+    }
+
+    #[test]
+    fn repr_forms() {
+        let input: syn::DeriveInput = syn::parse_quote! {
+            #[repr(C, u8)]
+            #[repr(align(8))]
+            enum State { A, B }
+        };
+        let repr = parse_repr(&input.attrs).unwrap();
+        assert!(repr.c && !repr.transparent);
+        assert_eq!(repr.int.unwrap(), "u8");
+        assert_eq!((repr.packed, repr.align), (None, Some(8)));
+
+        let input: syn::DeriveInput = syn::parse_quote! {
+            #[repr(packed)]
+            struct A(u8);
+        };
+        assert_eq!(parse_repr(&input.attrs).unwrap().packed, Some(1));
+        let input: syn::DeriveInput = syn::parse_quote! {
+            #[repr(transparent, packed(2))]
+            struct A(u8);
+        };
+        let repr = parse_repr(&input.attrs).unwrap();
+        assert!(repr.transparent);
+        assert_eq!(repr.packed, Some(2));
+
+        let input: syn::DeriveInput = syn::parse_quote! {
+            #[repr(simd)]
+            struct A(u8);
+        };
+        let err = parse_repr(&input.attrs).unwrap_err();
+        assert_eq!(err.to_string(), "unknown repr #parse_repr");
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+
+use crate::internals::ast::{AttrEntry, Container, Data, Val};
+use crate::internals::ctxt::Ctxt;
+use crate::internals::parse::{merge_map, Options};
+
+// #[sim(include = "models/bass.toml")] on the container, a variant or a field loads the file
+// relative to CARGO_MANIFEST_DIR and merges its top level keys into that node by Options::merge,
+// after the attrs written inline. TOML, or JSON when the file ends with `.json`.
+// The file of a container can carry the attrs of its fields and variants as well:
+// method = "system_dynamics"
+// [fields.sales]
+// flow = { from = "potential_clients", to = "clients", val = "sales_from_ad + sales_from_wom" }
+// [fields.clients]
+// stock = {}
+// an empty table stands for a marker like #[sim(stock)].
+// The file of a variant can carry [fields.<name>] the same way.
+
+/// The keys of one included file, with the include attr it came from.
+pub(crate) struct Loaded {
+    attrs: HashMap<String, Val>,
+    span: Span,
+    attr_index: usize,
+}
+
+const FIELDS: &str = "fields";
+const VARIANTS: &str = "variants";
+
+/// Take the include key out of `attrs` and `entries`, load the files it names.
+pub(crate) fn load_includes(
+    cx: &Ctxt,
+    opts: &Options,
+    attrs: &mut HashMap<String, Val>,
+    entries: &mut Vec<AttrEntry>,
+    includes: &mut Vec<PathBuf>,
+) -> Vec<Loaded> {
+    let key = match opts.include_key {
+        Some(key) => key.0,
+        None => return vec![],
+    };
+    if attrs.remove(key).is_none() {
+        return vec![];
+    }
+    let (found, rest): (Vec<_>, Vec<_>) = entries.drain(..).partition(|entry| entry.key == key);
+    *entries = rest;
+
+    let mut loaded = vec![];
+    for entry in found {
+        let rel = match &entry.val {
+            Val::Str(rel) => rel,
+            _ => {
+                let msg = format!("expect {key} = \"path\" #load_includes");
+                cx.syn_error(syn::Error::new(entry.span, msg));
+                continue;
+            }
+        };
+        let path = manifest_dir().join(rel);
+        match load_file(&path) {
+            Ok(attrs) => {
+                includes.push(path);
+                loaded.push(Loaded {
+                    attrs,
+                    span: entry.span,
+                    attr_index: entry.attr_index,
+                });
+            }
+            Err(msg) => {
+                let msg = format!("{msg}, {} #load_includes", path.display());
+                cx.syn_error(syn::Error::new(entry.span, msg));
+            }
+        }
+    }
+    loaded
+}
+
+fn manifest_dir() -> PathBuf {
+    std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+}
+
+fn load_file(path: &Path) -> Result<HashMap<String, Val>, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| format!("cannot read include: {err}"))?;
+    let val = if path.extension().is_some_and(|ext| ext == "json") {
+        Val::Str(text).parse_embedded().map_err(|err| err.to_string())?
+    } else {
+        let table: toml::Table = text.parse().map_err(|err| format!("invalid toml: {err}"))?;
+        val_from_toml(toml::Value::Table(table))
+    };
+    match val {
+        Val::Map(map) => Ok(map),
+        _ => Err("the include must be a table".to_string()),
+    }
+}
+
+fn val_from_toml(value: toml::Value) -> Val {
+    match value {
+        toml::Value::String(s) => Val::Str(s),
+        toml::Value::Integer(i) => Val::Int(i),
+        toml::Value::Float(f) => Val::Float(f),
+        toml::Value::Boolean(b) => Val::Bool(b),
+        toml::Value::Datetime(dt) => Val::Str(dt.to_string()),
+        toml::Value::Array(vs) => Val::Vec(vs.into_iter().map(val_from_toml).collect()),
+        toml::Value::Table(table) if table.is_empty() => Val::Empty,
+        toml::Value::Table(table) => Val::Map(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, val_from_toml(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// Merge the loaded keys into a node, an entry for each key points at the include attr.
+pub(crate) fn merge_loaded(
+    cx: &Ctxt,
+    opts: &Options,
+    loaded: Vec<Loaded>,
+    attrs: &mut HashMap<String, Val>,
+    entries: &mut Vec<AttrEntry>,
+) {
+    for Loaded {
+        attrs: from,
+        span,
+        attr_index,
+    } in loaded
+    {
+        // a key seen twice before is a Val::Vec of its vals by now.
+        let mut collected = HashSet::new();
+        for key in from.keys() {
+            if entries.iter().filter(|entry| entry.key == *key).nth(1).is_some() {
+                collected.insert(key.clone());
+            }
+        }
+        entries.extend(from.iter().map(|(key, val)| AttrEntry {
+            key: key.clone(),
+            val: val.clone(),
+            span,
+            attr_index,
+        }));
+        merge_map(cx, opts.merge, from, span, attrs, &mut collected);
+    }
+}
+
+/// Split the `[fields.<name>]` or `[variants.<name>]` tables off the loaded files.
+pub(crate) fn take_sections(loaded: &mut [Loaded], section: &str) -> HashMap<String, Vec<Loaded>> {
+    let mut sections: HashMap<String, Vec<Loaded>> = HashMap::new();
+    for file in loaded {
+        if let Some(Val::Map(nodes)) = file.attrs.remove(section) {
+            for (name, val) in nodes {
+                if let Val::Map(attrs) = val {
+                    sections.entry(name).or_default().push(Loaded {
+                        attrs,
+                        span: file.span,
+                        attr_index: file.attr_index,
+                    });
+                }
+            }
+        }
+    }
+    sections
+}
+
+/// Resolve the includes of the container, its variants and fields.
+pub(crate) fn include_attrs(cx: &Ctxt, opts: &Options, cont: &mut Container) {
+    let mut includes = vec![];
+    let mut loaded = load_includes(cx, opts, &mut cont.attrs, &mut cont.entries, &mut includes);
+    let mut fields = take_sections(&mut loaded, FIELDS);
+    let mut variants = take_sections(&mut loaded, VARIANTS);
+    merge_loaded(cx, opts, loaded, &mut cont.attrs, &mut cont.entries);
+
+    match &mut cont.data {
+        Data::Enum(vs) => {
+            for variant in vs {
+                let mut loaded = load_includes(
+                    cx,
+                    opts,
+                    &mut variant.attrs,
+                    &mut variant.entries,
+                    &mut includes,
+                );
+                loaded.extend(variants.remove(&variant.ident.to_string()).unwrap_or_default());
+                let mut fields = take_sections(&mut loaded, FIELDS);
+                merge_loaded(cx, opts, loaded, &mut variant.attrs, &mut variant.entries);
+                for field in &mut variant.fields {
+                    let mut loaded =
+                        load_includes(cx, opts, &mut field.attrs, &mut field.entries, &mut includes);
+                    loaded.extend(fields.remove(&field.name()).unwrap_or_default());
+                    merge_loaded(cx, opts, loaded, &mut field.attrs, &mut field.entries);
+                }
+                for name in fields.keys() {
+                    let msg = format!("no field `{name}` in `{}` #include_attrs", variant.ident);
+                    cx.syn_error(syn::Error::new(variant.ident.span(), msg));
+                }
+            }
+        }
+        Data::Struct(_, fs) => {
+            for field in fs {
+                let mut loaded =
+                    load_includes(cx, opts, &mut field.attrs, &mut field.entries, &mut includes);
+                loaded.extend(fields.remove(&field.name()).unwrap_or_default());
+                merge_loaded(cx, opts, loaded, &mut field.attrs, &mut field.entries);
+            }
+        }
+    }
+    for name in fields.keys().chain(variants.keys()) {
+        let msg = format!("no field or variant `{name}` in `{}` #include_attrs", cont.ident);
+        cx.syn_error(syn::Error::new(cont.ident.span(), msg));
+    }
+    cont.includes = includes;
+}
+
+impl<'a> Container<'a> {
+    /// `include_bytes!` of every included file, put it in the expansion so cargo rebuilds
+    /// when they change.
+    pub fn include_tokens(&self) -> TokenStream {
+        let paths = self.includes.iter().map(|path| path.display().to_string());
+        quote! {
+            #(const _: &[u8] = include_bytes!(#paths);)*
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::internals::ast::Symbol;
+    use crate::internals::parse::from_ast_with;
+
+    use super::*;
+
+    // `text` in a file of the temp dir, the absolute path replaces CARGO_MANIFEST_DIR.
+    fn file(name: &str, text: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("derive-attr-parser-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, text).unwrap();
+        path.display().to_string()
+    }
+
+    fn opts() -> Options {
+        Options {
+            include_key: Some(Symbol("include")),
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn toml_with_field_sections() {
+        let path = file(
+            "bass.toml",
+            r#"
+method = "system_dynamics"
+steps = 10
+dt = 0.25
+[fields.sales]
+flow = { from = "potential_clients", val = "sales_from_ad" }
+[fields.clients]
+stock = {}
+"#,
+        );
+        let input: syn::DeriveInput = syn::parse_quote! {
+            #[sim(include = #path, name = "bass")]
+            struct Bass {
+                clients: f64,
+                #[sim(output)]
+                sales: f64,
+            }
+        };
+        let cx = Ctxt::new();
+        let cont = from_ast_with(&cx, &input, Symbol("sim"), &opts()).unwrap();
+        cx.check().unwrap();
+
+        assert!(matches!(&cont.attrs["method"], Val::Str(method) if method == "system_dynamics"));
+        assert!(matches!(cont.attrs["steps"], Val::Int(10)));
+        assert!(matches!(cont.attrs["dt"], Val::Float(dt) if dt == 0.25));
+        assert!(!cont.attrs.contains_key("include") && !cont.attrs.contains_key("fields"));
+        assert_eq!(cont.includes, [PathBuf::from(&path)]);
+        let clients = cont.field("clients").unwrap();
+        assert!(matches!(clients.attrs["stock"], Val::Empty));
+        let sales = cont.field("sales").unwrap();
+        assert!(sales.attrs.contains_key("output"));
+        let Val::Map(flow) = &sales.attrs["flow"] else { panic!("expect Val::Map") };
+        assert!(matches!(&flow["from"], Val::Str(from) if from == "potential_clients"));
+        // inline first, then the include.
+        let keys: Vec<_> = sales.entries.iter().map(|entry| entry.key.as_str()).collect();
+        assert_eq!(keys, ["output", "flow"]);
+    }
+
+    #[test]
+    fn json_with_variant_sections() {
+        let path = file(
+            "state.json",
+            r#"{"name": "health", "variants": {"Exposed": {"trans": {"to": "Infectious"},
+                "fields": {"days_left": {"rotate": {}}}}}}"#,
+        );
+        let input: syn::DeriveInput = syn::parse_quote! {
+            #[fsm(include = #path)]
+            enum State {
+                Susceptible,
+                Exposed { days_left: usize },
+            }
+        };
+        let cx = Ctxt::new();
+        let cont = from_ast_with(&cx, &input, Symbol("fsm"), &opts()).unwrap();
+        cx.check().unwrap();
+
+        assert!(matches!(&cont.attrs["name"], Val::Str(name) if name == "health"));
+        let Data::Enum(variants) = &cont.data else { panic!("expect Data::Enum") };
+        assert!(variants[0].attrs.is_empty());
+        let Val::Map(trans) = &variants[1].attrs["trans"] else { panic!("expect Val::Map") };
+        assert!(matches!(&trans["to"], Val::Str(to) if to == "Infectious"));
+        assert!(matches!(variants[1].fields[0].attrs["rotate"], Val::Map(ref map) if map.is_empty()));
+    }
+
+    fn errors(input: syn::DeriveInput) -> Vec<String> {
+        let cx = Ctxt::new();
+        let _ = from_ast_with(&cx, &input, Symbol("sim"), &opts());
+        let err = cx.check().unwrap_err();
+        err.into_iter().map(|err| err.to_string()).collect()
+    }
+
+    #[test]
+    fn missing_and_malformed_files() {
+        let missing = file("missing.toml", "");
+        std::fs::remove_file(&missing).unwrap();
+        let input = syn::parse_quote!(#[sim(include = #missing)] struct Bass;);
+        let msgs = errors(input);
+        assert!(msgs[0].starts_with("cannot read include: "), "{msgs:?}");
+        assert!(msgs[0].ends_with(&format!(", {missing} #load_includes")), "{msgs:?}");
+
+        let malformed = file("malformed.toml", "method = ");
+        let input = syn::parse_quote!(#[sim(include = #malformed)] struct Bass;);
+        let msgs = errors(input);
+        assert!(msgs[0].starts_with("invalid toml: "), "{msgs:?}");
+
+        let malformed = file("malformed.json", r#"{"method": }"#);
+        let input = syn::parse_quote!(#[sim(include = #malformed)] struct Bass;);
+        let msgs = errors(input);
+        assert!(msgs[0].contains("#Val.parse_embedded"), "{msgs:?}");
+
+        let list = file("list.json", "[1, 2]");
+        let input = syn::parse_quote!(#[sim(include = #list)] struct Bass;);
+        assert_eq!(errors(input), [format!("the include must be a table, {list} #load_includes")]);
+    }
+
+    #[test]
+    fn unknown_field_section() {
+        let path = file("unknown.toml", "[fields.sales]\nflow = {}\n");
+        let input = syn::parse_quote!(#[sim(include = #path)] struct Bass { clients: f64 });
+        assert_eq!(errors(input), ["no field or variant `sales` in `Bass` #include_attrs"]);
+    }
+}
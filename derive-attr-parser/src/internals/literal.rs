@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+
+use proc_macro2::Span;
+
+use crate::internals::ast::Val;
+
+// JSON/RON-like literals embedded in string vals, see `Options::embedded`.
+// r#"{key:"val", k2:8}"# parse into {"key": Val::Str("val"), "k2": Val::Int(8)}
+// r#"["eula", "newton", (1, 2.5), true, null]"#
+// parse into [Val::Str("eula"), Val::Str("newton"), Val::Vec([Val::Int(1), Val::Float(2.5)]), Val::Bool(true), Val::Empty]
+// keys are strings or bare idents, bare idents as vals are Val::Str, eg. RON enum `Eula`.
+// trailing commas and `_` in numbers are allowed.
+
+/// Parse the embedded literal in `lit`, errors point at the bad char inside `lit` when the
+/// compiler can span into literals, at `lit` otherwise.
+pub fn parse_embedded(lit: &syn::LitStr) -> syn::Result<Val> {
+    let value = lit.value();
+    parse_literal(&value).map_err(|(offset, msg)| {
+        let span = subspan(lit, &value, offset).unwrap_or_else(|| lit.span());
+        syn::Error::new(span, format!("{msg} at {offset} of {value:?} #parse_embedded"))
+    })
+}
+
+// Only maps offsets for literals without escapes, the value is the token text between quotes.
+fn subspan(lit: &syn::LitStr, value: &str, offset: usize) -> Option<Span> {
+    let token = lit.token();
+    let repr = token.to_string();
+    let start = repr.find('"')? + 1;
+    if repr.get(start..start + value.len())? != value {
+        return None;
+    }
+    let at = start + offset.min(value.len().saturating_sub(1));
+    token.subspan(at..at + 1)
+}
+
+impl Val {
+    /// Parse a `Val::Str` holding a JSON/RON-like literal into nested `Val`.
+    pub fn parse_embedded(&self) -> syn::Result<Val> {
+        match self {
+            Val::Str(s) => parse_literal(s).map_err(|(offset, msg)| {
+                syn::Error::new(Span::call_site(), format!("{msg} at {offset} of {s:?} #Val.parse_embedded"))
+            }),
+            _ => Err(syn::Error::new(Span::call_site(), "None Val::Str cannot parse embedded literal ")),
+        }
+    }
+}
+
+/// Whether `s` looks like an embedded map or seq.
+pub(crate) fn is_embedded(s: &str) -> bool {
+    let s = s.trim_start();
+    s.starts_with('{') || s.starts_with('[')
+}
+
+type Result<T> = std::result::Result<T, (usize, String)>;
+
+fn parse_literal(s: &str) -> Result<Val> {
+    let mut parser = Parser { src: s, pos: 0 };
+    let val = parser.value()?;
+    parser.skip_ws();
+    if parser.pos < s.len() {
+        return Err(parser.error("unexpected trailing chars"));
+    }
+    Ok(val)
+}
+
+struct Parser<'s> {
+    src: &'s str,
+    pos: usize,
+}
+
+impl<'s> Parser<'s> {
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn error(&self, msg: &str) -> (usize, String) {
+        match self.peek() {
+            Some(c) => (self.pos, format!("{msg}, found `{c}`")),
+            None => (self.pos, format!("{msg}, found end")),
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        self.skip_ws();
+        if self.peek() == Some(expected) {
+            self.bump();
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{expected}`")))
+        }
+    }
+
+    fn value(&mut self) -> Result<Val> {
+        self.skip_ws();
+        match self.peek() {
+            Some('{') => self.map(),
+            Some('[') => self.seq(']'),
+            Some('(') => self.seq(')'),
+            Some('"') => Ok(Val::Str(self.string()?)),
+            Some(c) if c == '-' || c == '+' || c.is_ascii_digit() => self.number(),
+            Some(c) if c == '_' || c.is_alphabetic() => Ok(match self.ident().as_str() {
+                "true" => Val::Bool(true),
+                "false" => Val::Bool(false),
+                "null" => Val::Empty,
+                ident => Val::Str(ident.to_string()),
+            }),
+            _ => Err(self.error("expected value")),
+        }
+    }
+
+    fn map(&mut self) -> Result<Val> {
+        self.expect('{')?;
+        let mut map = HashMap::new();
+        loop {
+            self.skip_ws();
+            let key = match self.peek() {
+                Some('}') => break,
+                Some('"') => self.string()?,
+                Some(c) if c == '_' || c.is_alphabetic() => self.ident(),
+                _ => return Err(self.error("expected key")),
+            };
+            self.expect(':')?;
+            map.insert(key, self.value()?);
+            if !self.comma_or(&'}')? {
+                break;
+            }
+        }
+        self.expect('}')?;
+        Ok(Val::Map(map))
+    }
+
+    fn seq(&mut self, close: char) -> Result<Val> {
+        self.bump();
+        let mut vs = vec![];
+        loop {
+            self.skip_ws();
+            if self.peek() == Some(close) {
+                break;
+            }
+            vs.push(self.value()?);
+            if !self.comma_or(&close)? {
+                break;
+            }
+        }
+        self.expect(close)?;
+        Ok(Val::Vec(vs))
+    }
+
+    // After an item, `true` when a `,` is consumed, `false` when `close` follows.
+    fn comma_or(&mut self, close: &char) -> Result<bool> {
+        self.skip_ws();
+        match self.peek() {
+            Some(',') => {
+                self.bump();
+                Ok(true)
+            }
+            Some(c) if c == *close => Ok(false),
+            _ => Err(self.error(&format!("expected `,` or `{close}`"))),
+        }
+    }
+
+    fn ident(&mut self) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c == '_' || c.is_alphanumeric()) {
+            self.bump();
+        }
+        self.src[start..self.pos].to_string()
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.bump();
+        let mut s = String::new();
+        loop {
+            let at = self.pos;
+            match self.bump() {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.bump() {
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('r') => s.push('\r'),
+                    Some(c @ ('"' | '\\' | '/')) => s.push(c),
+                    Some('u') => {
+                        let hex = self.src.get(self.pos..self.pos + 4).unwrap_or_default();
+                        match u32::from_str_radix(hex, 16).ok().and_then(char::from_u32) {
+                            Some(c) => {
+                                s.push(c);
+                                self.pos += 4;
+                            }
+                            None => return Err((at, "invalid unicode escape".to_string())),
+                        }
+                    }
+                    _ => return Err((at, "invalid escape".to_string())),
+                },
+                Some(c) => s.push(c),
+                None => return Err((at, "unterminated string".to_string())),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Val> {
+        let start = self.pos;
+        if matches!(self.peek(), Some('-' | '+')) {
+            self.bump();
+        }
+        let mut float = false;
+        while let Some(c) = self.peek() {
+            match c {
+                '0'..='9' | '_' => {}
+                '.' | 'e' | 'E' => float = true,
+                '-' | '+' if float => {}
+                _ => break,
+            }
+            self.bump();
+        }
+        let text = self.src[start..self.pos].replace('_', "");
+        let val = if float {
+            text.parse().map(Val::Float).ok()
+        } else {
+            text.parse().map(Val::Int).ok()
+        };
+        val.ok_or_else(|| (start, format!("invalid number `{text}`")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Val {
+        parse_literal(s).unwrap_or_else(|(offset, msg)| panic!("{msg} at {offset} of {s:?}"))
+    }
+
+    fn str_of(val: &Val) -> &str {
+        match val {
+            Val::Str(s) => s,
+            _ => panic!("expect Val::Str, found {val:?}"),
+        }
+    }
+
+    #[test]
+    fn nesting() {
+        let Val::Map(map) = parse(r#"{solver: {algo: "rk45", tol: [1e-6, 1_000]}, steps: (1, -2.5)}"#) else {
+            panic!("expect Val::Map");
+        };
+        let Val::Map(solver) = &map["solver"] else { panic!("expect Val::Map") };
+        assert_eq!(str_of(&solver["algo"]), "rk45");
+        let Val::Vec(tol) = &solver["tol"] else { panic!("expect Val::Vec") };
+        assert!(matches!(tol[..], [Val::Float(f), Val::Int(1000)] if f == 1e-6));
+        let Val::Vec(steps) = &map["steps"] else { panic!("expect Val::Vec") };
+        assert!(matches!(steps[..], [Val::Int(1), Val::Float(f)] if f == -2.5));
+    }
+
+    #[test]
+    fn scalars() {
+        let Val::Vec(vs) = parse(r#"[true, false, null, Eula, "eula", +3]"#) else {
+            panic!("expect Val::Vec");
+        };
+        assert!(matches!(vs[..3], [Val::Bool(true), Val::Bool(false), Val::Empty]));
+        assert_eq!(str_of(&vs[3]), "Eula");
+        assert_eq!(str_of(&vs[4]), "eula");
+        assert!(matches!(vs[5], Val::Int(3)));
+    }
+
+    #[test]
+    fn escapes() {
+        let Val::Vec(vs) = parse(r#"["a\"b", "\\n\t", "\u00e9/\/", "é"]"#) else {
+            panic!("expect Val::Vec");
+        };
+        assert_eq!(str_of(&vs[0]), "a\"b");
+        assert_eq!(str_of(&vs[1]), "\\n\t");
+        assert_eq!(str_of(&vs[2]), "é//");
+        assert_eq!(str_of(&vs[3]), "é");
+    }
+
+    #[test]
+    fn trailing_commas() {
+        assert!(matches!(&parse("[1, 2,]"), Val::Vec(vs) if vs.len() == 2));
+        assert!(matches!(&parse("{a: 1, \"b\": 2 ,}"), Val::Map(map) if map.len() == 2));
+        assert!(matches!(&parse("( )"), Val::Vec(vs) if vs.is_empty()));
+        assert!(matches!(&parse(" {} "), Val::Map(map) if map.is_empty()));
+    }
+
+    #[test]
+    fn malformed() {
+        let cases = [
+            ("[1, 2", 5, "expected `,` or `]`, found end"),
+            ("[1,, 2]", 3, "expected value, found `,`"),
+            ("{a 1}", 3, "expected `:`, found `1`"),
+            ("{1: 2}", 1, "expected key, found `1`"),
+            ("{a: 1}}", 6, "unexpected trailing chars, found `}`"),
+            ("[\"abc]", 6, "unterminated string"),
+            ("[\"\\q\"]", 2, "invalid escape"),
+            ("[\"\\u12\"]", 2, "invalid unicode escape"),
+            ("[1.2.3]", 1, "invalid number `1.2.3`"),
+            ("[1)", 2, "expected `,` or `]`, found `)`"),
+        ];
+        for (s, offset, msg) in cases {
+            match parse_literal(s) {
+                Ok(val) => panic!("{s:?} parse into {val:?}"),
+                Err(err) => assert_eq!(err, (offset, msg.to_string()), "{s:?}"),
+            }
+        }
+    }
+}
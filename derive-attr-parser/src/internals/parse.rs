@@ -1,12 +1,18 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use proc_macro2::Span;
 use syn::meta::ParseNestedMeta;
 use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
 use syn::{token, Attribute, DeriveInput, Error, Token};
 
-use crate::internals::ast::{Container, Data, Field, Style, Symbol, Val, Variant};
+use crate::internals::ast::{
+    AttrEntry, AttrLevel, Container, Data, Field, Inherited, Style, Symbol, Val, Variant,
+};
 use crate::internals::ctxt::Ctxt;
+use crate::internals::include::include_attrs;
+use crate::internals::template::{check_placeholders, has_placeholder, interpolate_attrs};
+use crate::internals::literal::{is_embedded, parse_embedded};
 
 // The attr should keep simple as following supported literal
 // you can process string val as you want after extract the meta from attr,
@@ -23,30 +29,146 @@ use crate::internals::ctxt::Ctxt;
 // #[sim(ode_solver("eula", "10"))] X
 // #[sim(ode_solver["eula", "modified_newton"))] X
 // #[sim(ode_solver=["eula", "modified_newton"))] X
+// root forms:
+// #[sim] marker, parse into {"sim": Val::Empty}
+// #[sim = "Population"] shorthand, parse into {Options::default_key: Val::Str("Population")}
+// #[sim()] error, use #[sim] for a marker
 pub fn from_ast<'a>(
     cx: &Ctxt,
     input: &'a DeriveInput,
     root: Symbol,
 ) -> Result<Container<'a>, Error> {
-    container_from_ast(cx, input, root)
+    from_ast_with(cx, input, root, &Options::default())
+}
+
+/// Same as [`from_ast`], with [`Options`] to tune the parsing.
+pub fn from_ast_with<'a>(
+    cx: &Ctxt,
+    input: &'a DeriveInput,
+    root: Symbol,
+    opts: &Options,
+) -> Result<Container<'a>, Error> {
+    container_from_ast(cx, input, root, opts)
+}
+
+/// Options for [`from_ast_with`], `Options::default()` behaves as [`from_ast`].
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The key `#[root = "..."]` stored under, defaults to `name`.
+    ///
+    /// `#[sim = "Population"]` parse into {"name": Val::Str("Population")}
+    pub default_key: Symbol,
+    /// Look through `#[cfg_attr(pred, root(...))]`, defaults to `false`.
+    ///
+    /// The keys found that way record `pred` in `cfgs` of the node, nested `cfg_attr` combine
+    /// into `all(..)`, a key from several `cfg_attr` into `any(..)`.
+    ///
+    /// Only input rustc has not configured yet sees a `cfg_attr`: the fields of an attribute
+    /// macro item, or a `DeriveInput` parsed from source by a function-like macro or build
+    /// script. A derive never does, rustc expands `cfg_attr` before the derive runs, a true
+    /// `pred` leaves a plain `#[root(..)]` and a false one nothing, so `cfgs` stays empty.
+    pub cfg_attr: bool,
+    /// The key doc comments fallback to, defaults to `None`.
+    ///
+    /// With `Some(Symbol("description"))`, `/// Total population` parse into
+    /// {"description": Val::Str("Total population")} unless `description` is given explicitly.
+    pub doc_key: Option<Symbol>,
+    /// Keys the variants and fields inherit, defaults to empty.
+    ///
+    /// The nearest level wins: field over variant over container. An inherited val replaces,
+    /// it never merges with the one of another level. See [`Field::effective_attrs`].
+    pub inherit: Vec<Symbol>,
+    /// Parse string vals starting with `{` or `[` as JSON/RON-like literal, defaults to `false`.
+    ///
+    /// `algos = r#"{key:"val", k2:8}"#` parse into
+    /// {"algos": Val::Map({"key": Val::Str("val"), "k2": Val::Int(8)})}
+    pub embedded: bool,
+    /// The key naming files to load attrs from, eg. `Some(Symbol("include"))`, defaults to `None`.
+    ///
+    /// `#[sim(include = "models/bass.toml")]` see [`Container::include_tokens`].
+    pub include_key: Option<Symbol>,
+    /// How a repeated key merges, defaults to [`MergePolicy::Append`].
+    pub merge: MergePolicy,
+    /// Resolve `{ident}`, `{variant}`, `{field}` and `{env:VAR}` in string vals, defaults to
+    /// `false`.
+    ///
+    /// `#[sim(input_name = "{ident}Input")]` on `Bass` parse into {"input_name": Val::Str("BassInput")}
+    ///
+    /// Another name in braces, like `{idnet}`, is an error at the literal, other braces are
+    /// left as written. With `embedded`, a string with placeholders is parsed as embedded
+    /// literal once they resolve. Rustc doesn't track the env vars a proc macro reads, put
+    /// [`Container::env_tokens`] in the expansion to rebuild when they change.
+    pub interpolate: bool,
+    /// Other names of the root, parsed as if written as the root, defaults to empty.
+    ///
+    /// With `vec![Symbol("sim")]` for root `demo`, `#[sim(stock)]` parse the same as `#[demo(stock)]`,
+    /// a marker `#[sim]` into {"demo": Val::Empty}.
+    pub aliases: Vec<Symbol>,
+    /// The helper attributes the derive registers, defaults to empty.
+    ///
+    /// A helper attr that is neither the root nor an alias is reported, instead of silently
+    /// ignored. Keep it in line with `#[proc_macro_derive(Name, attributes(...))]`.
+    pub helpers: Vec<Symbol>,
+}
+
+/// How a key merges with the same key seen before, in source order, includes last.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Collect all the vals into a `Val::Vec`, a `Val::Vec` val, eg. embedded, is one element.
+    #[default]
+    Append,
+    /// Keep the val seen first, eg. inline attrs over includes.
+    KeepFirst,
+    /// Keep the val seen last, eg. includes over inline attrs.
+    Replace,
+    /// Report the repeated key as an error.
+    Error,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            default_key: Symbol("name"),
+            cfg_attr: false,
+            doc_key: None,
+            inherit: vec![],
+            embedded: false,
+            include_key: None,
+            merge: MergePolicy::Append,
+            interpolate: false,
+            aliases: vec![],
+            helpers: vec![],
+        }
+    }
 }
 
 fn container_from_ast<'a>(
     cx: &Ctxt,
     input: &'a DeriveInput,
     root: Symbol,
+    opts: &Options,
 ) -> Result<Container<'a>, Error> {
-    let attrs = parse_attrs(cx, &input.attrs, root)?;
-    let res = data_from_ast(cx, &input, root);
+    let parsed = parse_attrs(cx, &input.attrs, root, opts)?;
+    let res = data_from_ast(cx, input, root, opts);
     if let Some(data) = res {
         //eprintln!("{root} {attrs:#?}");
-        let item = Container {
+        let mut item = Container {
             ident: input.ident.clone(),
-            attrs,
+            attrs: parsed.attrs,
+            cfgs: parsed.cfgs,
+            doc: parsed.doc,
+            entries: parsed.entries,
+            includes: vec![],
+            envs: vec![],
             data,
             generics: &input.generics,
-            original: &input,
+            original: input,
         };
+        include_attrs(cx, opts, &mut item);
+        if opts.interpolate {
+            interpolate_attrs(cx, opts, &mut item);
+        }
+        inherit_attrs(opts, &item.attrs, &mut item.data);
         Ok(item)
     } else {
         Err(Error::new(
@@ -56,31 +178,75 @@ fn container_from_ast<'a>(
     }
 }
 
-fn parse_sub_attrs(cx: &Ctxt, meta: &ParseNestedMeta) -> syn::Result<HashMap<String, Val>> {
+// Fill `inherited` of variants and fields with the `Options::inherit` keys of their parents.
+fn inherit_attrs(opts: &Options, attrs: &HashMap<String, Val>, data: &mut Data) {
+    let from_container = inherited_from(opts, attrs, AttrLevel::Container, &HashMap::new());
+    match data {
+        Data::Enum(variants) => {
+            for variant in variants {
+                let from_variant =
+                    inherited_from(opts, &variant.attrs, AttrLevel::Variant, &from_container);
+                for field in &mut variant.fields {
+                    field.inherited = from_variant.clone();
+                }
+                variant.inherited = from_container.clone();
+            }
+        }
+        Data::Struct(_, fields) => {
+            for field in fields {
+                field.inherited = from_container.clone();
+            }
+        }
+    }
+}
+
+fn inherited_from(
+    opts: &Options,
+    attrs: &HashMap<String, Val>,
+    level: AttrLevel,
+    parent: &HashMap<String, Inherited>,
+) -> HashMap<String, Inherited> {
+    let mut inherited = parent.clone();
+    for key in &opts.inherit {
+        if let Some(val) = attrs.get(key.0) {
+            let val = val.clone();
+            inherited.insert(key.to_string(), Inherited { level, val });
+        }
+    }
+    inherited
+}
+
+fn parse_sub_attrs(
+    cx: &Ctxt,
+    meta: &ParseNestedMeta,
+    opts: &Options,
+) -> syn::Result<HashMap<String, Val>> {
     let lookahead = meta.input.lookahead1();
     let mut attrs = HashMap::new();
     if let Some(ident) = meta.path.get_ident() {
         let key = ident.to_string();
         // #[sim(ode_solver = "eula")]
         if lookahead.peek(Token![=]) {
-            attrs.insert(key, get_val_str(&meta)?);
+            attrs.insert(key, get_val_str(cx, meta, opts)?);
         } else if lookahead.peek(token::Paren) {
             // #[sim(ode_solver(algo = "eula", steps = "10"))]
             let mut all_sub_attrs = HashMap::new();
+            let mut collected = HashSet::new();
             if let Err(err) = meta.parse_nested_meta(|m| {
-                merge_map(cx, parse_sub_attrs(cx, &m)?, &mut all_sub_attrs);
+                let from = parse_sub_attrs(cx, &m, opts)?;
+                merge_map(cx, opts.merge, from, m.path.span(), &mut all_sub_attrs, &mut collected);
                 Ok(())
             }) {
                 cx.syn_error(err);
             }
             attrs.insert(key, Val::Map(all_sub_attrs));
         } else if lookahead.peek(Token![:]) {
-            attrs.insert(key, get_val_str(&meta)?);
+            attrs.insert(key, get_val_str(cx, meta, opts)?);
         } else {
             attrs.insert(key, Val::Empty);
         }
     } else {
-        let msg = format!("no ident found #parse_sub_attrs");
+        let msg = "no ident found #parse_sub_attrs";
         let err = Error::new(Span::call_site(), msg);
         cx.syn_error(err);
     }
@@ -88,7 +254,7 @@ fn parse_sub_attrs(cx: &Ctxt, meta: &ParseNestedMeta) -> syn::Result<HashMap<Str
     Ok(attrs)
 }
 
-fn get_val_str(meta: &ParseNestedMeta) -> syn::Result<Val> {
+fn get_val_str(cx: &Ctxt, meta: &ParseNestedMeta, opts: &Options) -> syn::Result<Val> {
     if let Err(eq) = meta.input.parse::<Token![=]>() {
         if let Err(_ec) = meta.input.parse::<Token![:]>() {
             let ident = meta.path.get_ident();
@@ -98,7 +264,40 @@ fn get_val_str(meta: &ParseNestedMeta) -> syn::Result<Val> {
         }
     }
     let expr: syn::Expr = meta.input.parse()?;
-    let mut value = &expr;
+    val_from_lit(cx, &expr, opts)
+}
+
+// With Options::interpolate, an unknown placeholder is reported here at the literal, and an
+// embedded literal with placeholders is parsed once they resolve, see interpolate_attrs.
+fn val_from_lit(cx: &Ctxt, expr: &syn::Expr, opts: &Options) -> syn::Result<Val> {
+    let lit = match lit_str(expr) {
+        Some(lit) => lit,
+        None => return Ok(val_from_expr(expr)),
+    };
+    let value = lit.value();
+    if opts.interpolate {
+        if let Err(msg) = check_placeholders(&value) {
+            cx.error_spanned_by(lit, msg);
+            return Ok(Val::Empty);
+        }
+    }
+    let deferred = opts.interpolate && has_placeholder(&value);
+    if opts.embedded && is_embedded(&value) && !deferred {
+        parse_embedded(lit)
+    } else {
+        Ok(Val::Str(value))
+    }
+}
+
+fn val_from_expr(expr: &syn::Expr) -> Val {
+    match lit_str(expr) {
+        Some(lit) => Val::Str(lit.value()),
+        None => Val::Str("".to_string()),
+    }
+}
+
+fn lit_str(expr: &syn::Expr) -> Option<&syn::LitStr> {
+    let mut value = expr;
     while let syn::Expr::Group(e) = value {
         value = &e.expr;
     }
@@ -107,24 +306,27 @@ fn get_val_str(meta: &ParseNestedMeta) -> syn::Result<Val> {
                               ..
                           }) = value
     {
-        let suffix = lit.suffix();
-        if !suffix.is_empty() {}
-        Ok(Val::Str(lit.clone().value()))
+        Some(lit)
     } else {
-        Ok(Val::Str("".to_string()))
+        None
     }
 }
 
-fn data_from_ast<'a>(cx: &Ctxt, input: &'a DeriveInput, root: Symbol) -> Option<Data<'a>> {
+fn data_from_ast<'a>(
+    cx: &Ctxt,
+    input: &'a DeriveInput,
+    root: Symbol,
+    opts: &Options,
+) -> Option<Data<'a>> {
     let data = match &input.data {
-        syn::Data::Enum(data) => Data::Enum(enum_from_ast(cx, &data.variants, root)),
+        syn::Data::Enum(data) => Data::Enum(enum_from_ast(cx, &data.variants, root, opts)),
         syn::Data::Struct(data) => {
-            let (style, fields) = struct_from_ast(cx, &data.fields, root);
+            let (style, fields) = struct_from_ast(cx, &data.fields, root, opts);
             Data::Struct(style, fields)
         }
         syn::Data::Union(_) => {
-            let msg = format!("Does not support derive for unions#data_from_ast");
-            cx.error_spanned_by(input, &msg);
+            let msg = "Does not support derive for unions#data_from_ast";
+            cx.error_spanned_by(input, msg);
             return None;
         }
     };
@@ -136,13 +338,14 @@ fn struct_from_ast<'a>(
     cx: &Ctxt,
     fields: &'a syn::Fields,
     root: Symbol,
+    opts: &Options,
 ) -> (Style, Vec<Field<'a>>) {
     match fields {
-        syn::Fields::Named(fields) => (Style::Struct, fields_from_ast(cx, &fields.named, root)),
+        syn::Fields::Named(fields) => (Style::Struct, fields_from_ast(cx, &fields.named, root, opts)),
         syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
-            (Style::Newtype, fields_from_ast(cx, &fields.unnamed, root))
+            (Style::Newtype, fields_from_ast(cx, &fields.unnamed, root, opts))
         }
-        syn::Fields::Unnamed(fields) => (Style::Tuple, fields_from_ast(cx, &fields.unnamed, root)),
+        syn::Fields::Unnamed(fields) => (Style::Tuple, fields_from_ast(cx, &fields.unnamed, root, opts)),
         syn::Fields::Unit => (Style::Unit, Vec::new()),
     }
 }
@@ -151,51 +354,223 @@ fn fields_from_ast<'a>(
     cx: &Ctxt,
     fields: &'a Punctuated<syn::Field, Token![,]>,
     root: Symbol,
+    opts: &Options,
 ) -> Vec<Field<'a>> {
     fields
         .iter()
         .enumerate()
-        .map(|(i, field)| Field {
-            member: match &field.ident {
-                Some(ident) => syn::Member::Named(ident.clone()),
-                None => syn::Member::Unnamed(i.into()),
-            },
-            attrs: filed_from_ast(cx, i, field, root),
-            ty: &field.ty,
-            original: field,
+        .map(|(i, field)| {
+            let parsed = filed_from_ast(cx, i, field, root, opts);
+            Field {
+                member: match &field.ident {
+                    Some(ident) => syn::Member::Named(ident.clone()),
+                    None => syn::Member::Unnamed(i.into()),
+                },
+                attrs: parsed.attrs,
+                cfgs: parsed.cfgs,
+                doc: parsed.doc,
+                entries: parsed.entries,
+                inherited: HashMap::new(),
+                ty: &field.ty,
+                original: field,
+            }
         })
         .collect()
 }
 
-fn parse_attrs(
+/// Everything parsed from the attrs of one node.
+#[derive(Default)]
+pub(crate) struct Parsed {
+    pub(crate) attrs: HashMap<String, Val>,
+    pub(crate) cfgs: HashMap<String, syn::Meta>,
+    pub(crate) doc: Option<String>,
+    pub(crate) entries: Vec<AttrEntry>,
+    /// The keys of `attrs` a repeated key collected into a `Val::Vec`, see [`merge_map`].
+    pub(crate) collected: HashSet<String>,
+}
+
+pub(crate) fn parse_attrs(
     cx: &Ctxt,
-    attrs: &Vec<Attribute>,
+    attrs: &[Attribute],
     root: Symbol,
-) -> syn::Result<HashMap<String, Val>> {
-    let mut all = HashMap::new();
-    for attr in attrs {
-        if attr.path() != root {
+    opts: &Options,
+) -> syn::Result<Parsed> {
+    let mut parsed = Parsed::default();
+    // keys contributed by attr without cfg_attr, they never carry a cfg.
+    let mut unconditional = HashSet::new();
+    let mut docs = vec![];
+    for (attr_index, attr) in attrs.iter().enumerate() {
+        if attr.path() == DOC {
+            if let syn::Meta::NameValue(nv) = &attr.meta {
+                if let Val::Str(line) = val_from_expr(&nv.value) {
+                    docs.push(line);
+                }
+            }
             continue;
         }
-        if let syn::Meta::List(meta) = &attr.meta {
-            if meta.tokens.is_empty() {
-                continue;
+        if opts.cfg_attr && attr.path() == CFG_ATTR {
+            if let Err(err) = parse_cfg_attr(cx, &attr.meta, attr_index, None, root, opts, &mut parsed) {
+                cx.syn_error(err);
             }
+            continue;
         }
-        let mut attrs = HashMap::new();
-        if let Err(err) = attr.parse_nested_meta(|meta| {
-            // 解析子 attr
-            let sub_attrs = parse_sub_attrs(cx, &meta);
-            merge_map(cx, sub_attrs?, &mut attrs);
-            Ok(())
-        }) {
-            cx.syn_error(err);
+        if !is_root(attr.path(), root, opts) {
+            check_helper(cx, attr.path(), root, opts);
+            continue;
         }
-        merge_map(cx, attrs, &mut all)
+        let entries = parse_root_meta(cx, &attr.meta, attr_index, root, opts);
+        unconditional.extend(entries.iter().map(|entry| entry.key.clone()));
+        merge_entries(cx, opts, &entries, &mut parsed.attrs, &mut parsed.collected);
+        parsed.entries.extend(entries);
+    }
+    for key in unconditional {
+        parsed.cfgs.remove(&key);
+    }
+    parsed.doc = join_docs(&docs);
+    if let (Some(key), Some(doc)) = (opts.doc_key, &parsed.doc) {
+        parsed
+            .attrs
+            .entry(key.to_string())
+            .or_insert_with(|| Val::Str(doc.clone()));
     }
 
     // eprintln!("{root} {all:#?}");
-    Ok(all)
+    Ok(parsed)
+}
+
+const CFG_ATTR: Symbol = Symbol("cfg_attr");
+const DOC: Symbol = Symbol("doc");
+
+// `/// line` is `#[doc = " line"]`, drop the one space rustdoc also drops.
+fn join_docs(lines: &[String]) -> Option<String> {
+    let doc = lines
+        .iter()
+        .map(|line| line.strip_prefix(' ').unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let doc = doc.trim_end();
+    if doc.is_empty() {
+        None
+    } else {
+        Some(doc.to_string())
+    }
+}
+
+// #[cfg_attr(feature = "x", sim(...), cfg_attr(test, sim(...)))]
+fn parse_cfg_attr(
+    cx: &Ctxt,
+    meta: &syn::Meta,
+    attr_index: usize,
+    outer: Option<&syn::Meta>,
+    root: Symbol,
+    opts: &Options,
+    parsed: &mut Parsed,
+) -> syn::Result<()> {
+    let (pred, metas) = meta.require_list()?.parse_args_with(|input: syn::parse::ParseStream| {
+        let pred: syn::Meta = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let metas = Punctuated::<syn::Meta, Token![,]>::parse_terminated(input)?;
+        Ok((pred, metas))
+    })?;
+    let pred: syn::Meta = match outer {
+        Some(outer) => syn::parse_quote!(all(#outer, #pred)),
+        None => pred,
+    };
+    for meta in &metas {
+        if meta.path() == CFG_ATTR {
+            parse_cfg_attr(cx, meta, attr_index, Some(&pred), root, opts, parsed)?;
+        } else if is_root(meta.path(), root, opts) {
+            let entries = parse_root_meta(cx, meta, attr_index, root, opts);
+            for entry in &entries {
+                let cfg = match parsed.cfgs.remove(&entry.key) {
+                    Some(prev) => syn::parse_quote!(any(#prev, #pred)),
+                    None => pred.clone(),
+                };
+                parsed.cfgs.insert(entry.key.clone(), cfg);
+            }
+            merge_entries(cx, opts, &entries, &mut parsed.attrs, &mut parsed.collected);
+            parsed.entries.extend(entries);
+        } else {
+            check_helper(cx, meta.path(), root, opts);
+        }
+    }
+    Ok(())
+}
+
+fn is_root(path: &syn::Path, root: Symbol, opts: &Options) -> bool {
+    path == root || opts.aliases.iter().any(|alias| path == *alias)
+}
+
+// A registered helper the parsing never looks at, eg. `attributes(demo)` parsed with root `sim`.
+fn check_helper(cx: &Ctxt, path: &syn::Path, root: Symbol, opts: &Options) {
+    if let Some(helper) = opts.helpers.iter().find(|helper| path == **helper) {
+        let msg = format!(
+            "#[{helper}] is a helper attribute of this derive but never parsed, the root is #[{root}] #parse_attrs"
+        );
+        cx.error_spanned_by(path, msg);
+    }
+}
+
+fn parse_root_meta(
+    cx: &Ctxt,
+    meta: &syn::Meta,
+    attr_index: usize,
+    root: Symbol,
+    opts: &Options,
+) -> Vec<AttrEntry> {
+    let mut entries = vec![];
+    let mut push = |key: String, val: Val, span: Span| {
+        entries.push(AttrEntry {
+            key,
+            val,
+            span,
+            attr_index,
+        })
+    };
+    match meta {
+        // #[sim]
+        syn::Meta::Path(path) => {
+            push(root.to_string(), Val::Empty, path.span());
+        }
+        // #[sim = "Population"]
+        syn::Meta::NameValue(nv) => {
+            match val_from_lit(cx, &nv.value, opts) {
+                Ok(val) => push(opts.default_key.to_string(), val, nv.value.span()),
+                Err(err) => cx.syn_error(err),
+            }
+        }
+        // #[sim()]
+        syn::Meta::List(list) if list.tokens.is_empty() => {
+            let msg = format!("empty #[{root}()], use #[{root}] for a marker #parse_attrs");
+            cx.error_spanned_by(meta, msg);
+        }
+        syn::Meta::List(list) => {
+            if let Err(err) = list.parse_nested_meta(|meta| {
+                // 解析子 attr
+                let span = meta.path.span();
+                for (key, val) in parse_sub_attrs(cx, &meta, opts)? {
+                    push(key, val, span);
+                }
+                Ok(())
+            }) {
+                cx.syn_error(err);
+            }
+        }
+    }
+    entries
+}
+
+fn merge_entries(
+    cx: &Ctxt,
+    opts: &Options,
+    entries: &[AttrEntry],
+    to: &mut HashMap<String, Val>,
+    collected: &mut HashSet<String>,
+) {
+    for entry in entries {
+        let from = HashMap::from([(entry.key.clone(), entry.val.clone())]);
+        merge_map(cx, opts.merge, from, entry.span, to, collected);
+    }
 }
 
 fn filed_from_ast(
@@ -203,22 +578,28 @@ fn filed_from_ast(
     _index: usize,
     field: &syn::Field,
     root: Symbol,
-) -> HashMap<String, Val> {
-    match parse_attrs(cx, &field.attrs, root) {
+    opts: &Options,
+) -> Parsed {
+    match parse_attrs(cx, &field.attrs, root, opts) {
         Ok(m) => m,
         Err(e) => {
             cx.error_spanned_by(field, e);
-            HashMap::new()
+            Parsed::default()
         }
     }
 }
 
-fn variant_from_ast(cx: &Ctxt, variant: &syn::Variant, root: Symbol) -> HashMap<String, Val> {
-    match parse_attrs(cx, &variant.attrs, root) {
+fn variant_from_ast(
+    cx: &Ctxt,
+    variant: &syn::Variant,
+    root: Symbol,
+    opts: &Options,
+) -> Parsed {
+    match parse_attrs(cx, &variant.attrs, root, opts) {
         Ok(map) => map,
         Err(e) => {
             cx.syn_error(e);
-            HashMap::new()
+            Parsed::default()
         }
     }
 }
@@ -227,15 +608,20 @@ fn enum_from_ast<'a>(
     cx: &Ctxt,
     variants: &'a Punctuated<syn::Variant, Token![,]>,
     root: Symbol,
+    opts: &Options,
 ) -> Vec<Variant<'a>> {
     let variants: Vec<Variant> = variants
         .iter()
         .map(|variant| {
-            let attrs = variant_from_ast(cx, variant, root);
-            let (style, fields) = struct_from_ast(cx, &variant.fields, root);
+            let parsed = variant_from_ast(cx, variant, root, opts);
+            let (style, fields) = struct_from_ast(cx, &variant.fields, root, opts);
             Variant {
                 ident: variant.ident.clone(),
-                attrs,
+                attrs: parsed.attrs,
+                cfgs: parsed.cfgs,
+                doc: parsed.doc,
+                entries: parsed.entries,
+                inherited: HashMap::new(),
                 style,
                 fields,
                 original: variant,
@@ -245,25 +631,452 @@ fn enum_from_ast<'a>(
     variants
 }
 
-fn merge_map(_cx: &Ctxt, from: HashMap<String, Val>, to: &mut HashMap<String, Val>) {
+// Append collects the vals of a repeated key into a `Val::Vec` and notes the key in `collected`,
+// a `Val::Vec` val of its own, eg. an embedded "[1, 2]", stays one element of it:
+// a = "[1, 2]", a = "[3]" parse into {"a": Val::Vec([Val::Vec([1, 2]), Val::Vec([3])])}
+// Error reports the repeated key at `span`, where `from` is written.
+pub(crate) fn merge_map(
+    cx: &Ctxt,
+    policy: MergePolicy,
+    from: HashMap<String, Val>,
+    span: Span,
+    to: &mut HashMap<String, Val>,
+    collected: &mut HashSet<String>,
+) {
     for (k, v) in from {
         if let Some(x) = to.get(&k) {
-            eprintln!("duplicated key {{{k}}} #merge_map");
-            let mut vs = vec![];
-            match x {
-                Val::Vec(v) => {
-                    vs.append(&mut v.to_vec())
-                }
-                _ => {
-                    vs.push(x.clone());
+            match policy {
+                MergePolicy::Append => {
+                    let mut vs = match x {
+                        Val::Vec(prev) if collected.contains(&k) => prev.clone(),
+                        _ => vec![x.clone()],
+                    };
                     vs.push(v);
+                    to.insert(k.clone(), Val::Vec(vs));
+                    collected.insert(k);
                 }
-            };
-            to.insert(k, Val::Vec(vs));
-            // let msg = format!("duplicated key {{{k}}}#merge_map");
-            // cx.syn_error(Error::new(Span::call_site(), msg));
+                MergePolicy::KeepFirst => {}
+                MergePolicy::Replace => {
+                    to.insert(k, v);
+                }
+                MergePolicy::Error => {
+                    let msg = format!("duplicated key {{{k}}}#merge_map");
+                    cx.syn_error(Error::new(span, msg));
+                }
+            }
         } else {
             to.insert(k, v);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use quote::{quote, ToTokens};
+
+    use super::*;
+
+    fn cfg_of(cfgs: &HashMap<String, syn::Meta>, key: &str) -> Option<String> {
+        cfgs.get(key).map(|cfg| cfg.to_token_stream().to_string())
+    }
+
+    #[test]
+    fn cfg_attr_records_the_predicate() {
+        // the fields of `#[sim_model] struct Bass { .. }` as an attribute macro receives them.
+        let input: DeriveInput = syn::parse_quote! {
+            #[sim(name = "bass")]
+            #[cfg_attr(feature = "x", sim(solver = "rk4"), cfg_attr(test, sim(steps = 10)))]
+            struct Bass {
+                #[cfg_attr(unix, sim(stock))]
+                #[cfg_attr(windows, sim(stock))]
+                clients: f64,
+                #[sim(flow)]
+                #[cfg_attr(unix, sim(flow))]
+                sales: f64,
+            }
+        };
+        let opts = Options {
+            cfg_attr: true,
+            ..Options::default()
+        };
+        let cx = Ctxt::new();
+        let cont = from_ast_with(&cx, &input, Symbol("sim"), &opts).unwrap();
+        cx.check().unwrap();
+
+        assert!(cont.attrs.contains_key("solver"));
+        assert_eq!(cfg_of(&cont.cfgs, "name"), None);
+        assert_eq!(cfg_of(&cont.cfgs, "solver"), Some(quote!(feature = "x").to_string()));
+        assert_eq!(
+            cfg_of(&cont.cfgs, "steps"),
+            Some(quote!(all(feature = "x", test)).to_string())
+        );
+        let clients = cont.field("clients").unwrap();
+        assert_eq!(cfg_of(&clients.cfgs, "stock"), Some(quote!(any(unix, windows)).to_string()));
+        let sales = cont.field("sales").unwrap();
+        assert!(sales.attrs.contains_key("flow"));
+        assert_eq!(cfg_of(&sales.cfgs, "flow"), None);
+    }
+
+    // `[a, [1, 2]]` for Val::Vec([Val::Str("a"), Val::Vec([Val::Int(1), Val::Int(2)])])
+    fn shape(val: &Val) -> String {
+        match val {
+            Val::Str(s) => s.clone(),
+            Val::Int(i) => i.to_string(),
+            Val::Vec(vs) => format!("[{}]", vs.iter().map(shape).collect::<Vec<_>>().join(", ")),
+            _ => format!("{val:?}"),
+        }
+    }
+
+    #[test]
+    fn repeated_key_keeps_embedded_vecs_whole() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[sim(a = "[1, 2]", a = "[3]", b = "[1, 2]", b = "3", c = "x")]
+            #[sim(c = "y", c = "[z]", d(e = "[1]", e = "[2]", e = "[3]"))]
+            struct Bass;
+        };
+        let opts = Options {
+            embedded: true,
+            ..Options::default()
+        };
+        let cx = Ctxt::new();
+        let cont = from_ast_with(&cx, &input, Symbol("sim"), &opts).unwrap();
+        cx.check().unwrap();
+
+        assert_eq!(shape(&cont.attrs["a"]), "[[1, 2], [3]]");
+        assert_eq!(shape(&cont.attrs["b"]), "[[1, 2], 3]");
+        assert_eq!(shape(&cont.attrs["c"]), "[x, y, [z]]");
+        let Val::Map(d) = &cont.attrs["d"] else { panic!("expect Val::Map") };
+        assert_eq!(shape(&d["e"]), "[[1], [2], [3]]");
+    }
+
+    #[test]
+    fn merge_error_points_at_the_repeated_key() {
+        let input: DeriveInput = syn::parse_str(
+            "#[sim(a = 1, b(c = 2))]\n#[sim(b(c = 3, c = 4), a = 5)]\nstruct Bass;",
+        )
+        .unwrap();
+        let opts = Options {
+            merge: MergePolicy::Error,
+            ..Options::default()
+        };
+        let cx = Ctxt::new();
+        let _ = from_ast_with(&cx, &input, Symbol("sim"), &opts);
+        let err = cx.check().unwrap_err();
+        let at: Vec<_> = err
+            .into_iter()
+            .map(|err| {
+                let start = err.span().start();
+                (err.to_string(), start.line, start.column)
+            })
+            .collect();
+        assert_eq!(
+            at,
+            [
+                ("duplicated key {c}#merge_map".to_string(), 2, 15),
+                ("duplicated key {b}#merge_map".to_string(), 2, 6),
+                ("duplicated key {a}#merge_map".to_string(), 2, 23),
+            ]
+        );
+    }
+
+    #[test]
+    fn placeholders_resolve_before_embedded_literals() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[sim(input_name = "{ident}Input", names = r#"["{ident}", "b"]"#, algos = r#"{k: 8}"#)]
+            struct Bass {
+                #[sim(label = r#"{name: "{field}"}"#)]
+                clients: f64,
+            }
+        };
+        let opts = Options {
+            embedded: true,
+            interpolate: true,
+            ..Options::default()
+        };
+        let cx = Ctxt::new();
+        let cont = from_ast_with(&cx, &input, Symbol("sim"), &opts).unwrap();
+        cx.check().unwrap();
+
+        assert_eq!(shape(&cont.attrs["input_name"]), "BassInput");
+        assert_eq!(shape(&cont.attrs["names"]), "[Bass, b]");
+        let Val::Map(algos) = &cont.attrs["algos"] else { panic!("expect Val::Map") };
+        assert_eq!(shape(&algos["k"]), "8");
+        let clients = cont.field("clients").unwrap();
+        let Val::Map(label) = &clients.attrs["label"] else { panic!("expect Val::Map") };
+        assert_eq!(shape(&label["name"]), "clients");
+        // entries see the same.
+        assert_eq!(shape(&cont.entries[0].val), "BassInput");
+    }
+
+    #[test]
+    fn unknown_placeholder_points_at_the_literal() {
+        let input: DeriveInput =
+            syn::parse_str("#[sim(a = \"x\", input_name = \"{idnet}Input\")]\nstruct Bass;").unwrap();
+        let opts = Options {
+            interpolate: true,
+            ..Options::default()
+        };
+        let cx = Ctxt::new();
+        let _ = from_ast_with(&cx, &input, Symbol("sim"), &opts);
+        let err = cx.check().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown placeholder `{idnet}`, expect {ident}, {variant}, {field} or {env:VAR} \
+             in \"{idnet}Input\" #interpolate"
+        );
+        let start = err.span().start();
+        assert_eq!((start.line, start.column), (1, 28));
+    }
+
+    #[test]
+    fn doc_comments_are_joined() {
+        let input: DeriveInput = syn::parse_quote! {
+            /// The Bass diffusion.
+            ///
+            ///   indented
+            #[sim(name = "bass")]
+            enum Bass {
+                /// Ad driven.
+                Ad {
+                    /// People.
+                    #[sim(description = "given")]
+                    clients: f64,
+                    /// Sold.
+                    sales: f64,
+                },
+                #[doc = ""]
+                Word,
+            }
+        };
+        let opts = Options {
+            doc_key: Some(Symbol("description")),
+            ..Options::default()
+        };
+        let cx = Ctxt::new();
+        let cont = from_ast_with(&cx, &input, Symbol("sim"), &opts).unwrap();
+        cx.check().unwrap();
+
+        assert_eq!(cont.doc.as_deref(), Some("The Bass diffusion.\n\n  indented"));
+        let Data::Enum(variants) = &cont.data else { panic!("expect Data::Enum") };
+        assert_eq!(variants[0].doc.as_deref(), Some("Ad driven."));
+        // blank docs are none.
+        assert_eq!(variants[1].doc, None);
+        assert!(!variants[1].attrs.contains_key("description"));
+        let clients = cont.field("clients").unwrap();
+        assert_eq!(clients.doc.as_deref(), Some("People."));
+        // the key written wins over the doc.
+        assert_eq!(shape(&clients.attrs["description"]), "given");
+        assert_eq!(shape(&cont.field("sales").unwrap().attrs["description"]), "Sold.");
+        assert_eq!(shape(&cont.attrs["description"]), "The Bass diffusion.\n\n  indented");
+        // without doc_key the docs stay out of attrs.
+        let cx = Ctxt::new();
+        let cont = from_ast(&cx, &input, Symbol("sim")).unwrap();
+        cx.check().unwrap();
+        assert!(!cont.attrs.contains_key("description"));
+        assert!(cont.field("sales").unwrap().attrs.is_empty());
+    }
+
+    #[test]
+    fn entries_keep_every_occurrence_in_order() {
+        let input: DeriveInput = syn::parse_str(
+            "#[fsm(trans(to = \"B\"))]\n#[derive(Debug)]\n#[fsm(rotate, trans(to = \"C\"), trans(to = \"D\"))]\nenum Fsm {\n    /// first\n    #[fsm(trans(to = \"A\"))]\n    A,\n}",
+        )
+        .unwrap();
+        let cx = Ctxt::new();
+        let cont = from_ast(&cx, &input, Symbol("fsm")).unwrap();
+        cx.check().unwrap();
+
+        let at: Vec<_> = cont
+            .entries
+            .iter()
+            .map(|entry| {
+                let start = entry.span.start();
+                (entry.key.as_str(), entry.attr_index, start.line, start.column)
+            })
+            .collect();
+        assert_eq!(
+            at,
+            [("trans", 0, 1, 6), ("rotate", 2, 3, 6), ("trans", 2, 3, 14), ("trans", 2, 3, 31)]
+        );
+        let to: Vec<_> = cont
+            .entries
+            .iter()
+            .filter(|entry| entry.key == "trans")
+            .map(|entry| match &entry.val {
+                Val::Map(map) => shape(&map["to"]),
+                val => shape(val),
+            })
+            .collect();
+        assert_eq!(to, ["B", "C", "D"]);
+        // the merged map keeps all three too.
+        assert_eq!(cont.attrs["trans"].as_slice().len(), 3);
+        // the doc comment counts as an attr.
+        let Data::Enum(variants) = &cont.data else { panic!("expect Data::Enum") };
+        assert_eq!(variants[0].entries.len(), 1);
+        assert_eq!(variants[0].entries[0].attr_index, 1);
+    }
+
+    #[test]
+    fn nearest_level_wins_for_inherited_keys() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[sim(name = "bass", ode_solver = "rk4", unit(of = "m", scale = "1"))]
+            enum Bass {
+                #[sim(unit(of = "s"))]
+                A {
+                    #[sim(ode_solver = "eula")]
+                    a: f64,
+                    b: f64,
+                },
+                B(f64),
+            }
+        };
+        let opts = Options {
+            inherit: vec![Symbol("ode_solver"), Symbol("unit")],
+            ..Options::default()
+        };
+        let cx = Ctxt::new();
+        let cont = from_ast_with(&cx, &input, Symbol("sim"), &opts).unwrap();
+        cx.check().unwrap();
+
+        let level = |field: &str, key: &str| {
+            let field = cont.field(field).unwrap();
+            field.effective_attrs().get(key).map(|(level, val)| (*level, shape(val)))
+        };
+        assert_eq!(level("a", "ode_solver"), Some((AttrLevel::Field, "eula".to_string())));
+        assert_eq!(level("b", "ode_solver"), Some((AttrLevel::Container, "rk4".to_string())));
+        assert_eq!(level("0", "ode_solver"), Some((AttrLevel::Container, "rk4".to_string())));
+        // only the declared keys.
+        assert_eq!(level("b", "name"), None);
+        // the variant val replaces the container map, `scale` is not merged in.
+        let b = cont.field("b").unwrap().effective_attrs();
+        let (unit_level, unit) = b["unit"];
+        assert_eq!(unit_level, AttrLevel::Variant);
+        assert!(!unit.as_map().unwrap().contains_key("scale"));
+        let Data::Enum(variants) = &cont.data else { panic!("expect Data::Enum") };
+        let a = variants[0].effective_attrs();
+        assert_eq!(a["unit"].0, AttrLevel::Variant);
+        assert_eq!(a["ode_solver"].0, AttrLevel::Container);
+        assert_eq!(variants[1].effective_attrs()["unit"].0, AttrLevel::Container);
+        // `inherited` itself only holds the parents.
+        assert!(variants[0].inherited["unit"].val.as_map().unwrap().contains_key("scale"));
+        assert_eq!(variants[0].inherited["unit"].level, AttrLevel::Container);
+    }
+
+    #[test]
+    fn aliases_parse_as_the_root() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[demo(name = "bass")]
+            #[sim(method = "system_dynamics")]
+            struct Bass {
+                #[sim]
+                #[demo(stock(val = "1.0"))]
+                clients: f64,
+            }
+        };
+        let opts = Options {
+            aliases: vec![Symbol("sim")],
+            helpers: vec![Symbol("demo"), Symbol("sim")],
+            ..Options::default()
+        };
+        let cx = Ctxt::new();
+        let cont = from_ast_with(&cx, &input, Symbol("demo"), &opts).unwrap();
+        cx.check().unwrap();
+        assert_eq!(shape(&cont.attrs["name"]), "bass");
+        assert_eq!(shape(&cont.attrs["method"]), "system_dynamics");
+        let clients = cont.field("clients").unwrap();
+        // the marker of an alias is keyed by the root.
+        assert!(matches!(clients.attrs["demo"], Val::Empty));
+        assert!(clients.attrs.contains_key("stock"));
+    }
+
+    #[test]
+    fn unparsed_helper_is_reported() {
+        let input: DeriveInput =
+            syn::parse_str("#[demo(name = \"bass\")]\nstruct Bass {\n    #[demo(stock)]\n    clients: f64,\n}")
+                .unwrap();
+        let opts = Options {
+            helpers: vec![Symbol("demo")],
+            ..Options::default()
+        };
+        let cx = Ctxt::new();
+        let _ = from_ast_with(&cx, &input, Symbol("sim"), &opts);
+        let at: Vec<_> = cx
+            .check()
+            .unwrap_err()
+            .into_iter()
+            .map(|err| (err.to_string(), err.span().start().line))
+            .collect();
+        let msg = "#[demo] is a helper attribute of this derive but never parsed, the root is #[sim] #parse_attrs";
+        assert_eq!(at, [(msg.to_string(), 1), (msg.to_string(), 3)]);
+        // without helpers a foreign attr is simply not ours.
+        let cx = Ctxt::new();
+        let cont = from_ast(&cx, &input, Symbol("sim")).unwrap();
+        cx.check().unwrap();
+        assert!(cont.attrs.is_empty());
+    }
+
+    #[test]
+    fn bare_root_is_a_marker() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[sim]
+            struct Bass {
+                #[sim]
+                clients: f64,
+            }
+        };
+        let cx = Ctxt::new();
+        let cont = from_ast(&cx, &input, Symbol("sim")).unwrap();
+        cx.check().unwrap();
+        assert!(matches!(cont.attrs["sim"], Val::Empty));
+        assert!(matches!(cont.field("clients").unwrap().attrs["sim"], Val::Empty));
+        assert_eq!(cont.entries[0].key, "sim");
+    }
+
+    #[test]
+    fn root_name_value_goes_to_the_default_key() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[sim = "Population"]
+            #[sim(solver = "rk4")]
+            struct Bass;
+        };
+        let cx = Ctxt::new();
+        let cont = from_ast(&cx, &input, Symbol("sim")).unwrap();
+        cx.check().unwrap();
+        assert_eq!(shape(&cont.attrs["name"]), "Population");
+        assert_eq!(shape(&cont.attrs["solver"]), "rk4");
+
+        let opts = Options {
+            default_key: Symbol("model"),
+            ..Options::default()
+        };
+        let cx = Ctxt::new();
+        let cont = from_ast_with(&cx, &input, Symbol("sim"), &opts).unwrap();
+        cx.check().unwrap();
+        assert_eq!(shape(&cont.attrs["model"]), "Population");
+        assert!(!cont.attrs.contains_key("name"));
+    }
+
+    #[test]
+    fn empty_root_list_is_an_error() {
+        let input: DeriveInput = syn::parse_str("#[sim(a = \"x\")]\n#[sim()]\nstruct Bass;").unwrap();
+        let cx = Ctxt::new();
+        let _ = from_ast(&cx, &input, Symbol("sim"));
+        let err = cx.check().unwrap_err();
+        assert_eq!(err.to_string(), "empty #[sim()], use #[sim] for a marker #parse_attrs");
+        let start = err.span().start();
+        assert_eq!((start.line, start.column), (2, 2));
+    }
+
+    #[test]
+    fn cfg_attr_is_skipped_by_default() {
+        let input: DeriveInput = syn::parse_quote! {
+            #[cfg_attr(feature = "x", sim(solver = "rk4"))]
+            struct Bass;
+        };
+        let cx = Ctxt::new();
+        let cont = from_ast(&cx, &input, Symbol("sim")).unwrap();
+        cx.check().unwrap();
+        assert!(cont.attrs.is_empty());
+        assert!(cont.cfgs.is_empty());
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use proc_macro2::Span;
+
+use crate::internals::ast::{AttrLevel, Container, Data, Field, Inherited, Val, Variant};
+
+// Dotted key paths over the parsed attrs, numbers index into Val::Vec of duplicated keys.
+// #[fsm(trans(to = "B"))] "trans.to" => Val::Str("B")
+// #[fsm(trans(to = "B"))] #[fsm(trans(to = "C"))] "trans.1.to" => Val::Str("C")
+
+impl Val {
+    /// Walk the dotted `path` down the nested `Val::Map` and `Val::Vec`.
+    pub fn get_path(&self, path: &str) -> syn::Result<&Val> {
+        self.get_path_from(path, 0, Span::call_site())
+    }
+
+    // `skip` segments of `path` are resolved already, only used for the error message.
+    fn get_path_from(&self, path: &str, skip: usize, span: Span) -> syn::Result<&Val> {
+        let mut val = self;
+        for (i, seg) in path.split('.').enumerate().skip(skip) {
+            let next = match val {
+                Val::Map(map) => map.get(seg),
+                Val::Vec(vs) => seg.parse::<usize>().ok().and_then(|idx| vs.get(idx)),
+                _ => None,
+            };
+            val = match next {
+                Some(next) => next,
+                None => {
+                    let found = path.split('.').take(i).collect::<Vec<_>>().join(".");
+                    let msg = format!("missing `{path}`, no `{seg}` in `{found}` #Val.get_path");
+                    return Err(syn::Error::new(span, msg));
+                }
+            };
+        }
+        Ok(val)
+    }
+
+    pub fn as_str(&self) -> syn::Result<&str> {
+        match self {
+            Val::Str(s) => Ok(s),
+            _ => Err(syn::Error::new(Span::call_site(), "None Val::Str cannot convert to str ")),
+        }
+    }
+
+    pub fn as_map(&self) -> syn::Result<&HashMap<String, Val>> {
+        match self {
+            Val::Map(map) => Ok(map),
+            _ => Err(syn::Error::new(Span::call_site(), "None Val::Map cannot convert to HashMap ")),
+        }
+    }
+
+    /// A `Val::Vec` as slice, any other val as a slice of itself.
+    pub fn as_slice(&self) -> &[Val] {
+        match self {
+            Val::Vec(vs) => vs,
+            _ => std::slice::from_ref(self),
+        }
+    }
+}
+
+/// Look up the dotted `path` in `attrs`, the first segment is the top level key.
+pub fn get_path<'v>(attrs: &'v HashMap<String, Val>, path: &str, span: Span) -> syn::Result<&'v Val> {
+    let key = path.split('.').next().unwrap_or_default();
+    match attrs.get(key) {
+        Some(val) => val.get_path_from(path, 1, span),
+        None => {
+            let msg = format!("missing `{path}`, no `{key}` in attrs #get_path");
+            Err(syn::Error::new(span, msg))
+        }
+    }
+}
+
+impl<'a> Data<'a> {
+    /// The fields of the struct, or the fields of all variants of the enum.
+    pub fn all_fields(&self) -> impl Iterator<Item = &Field<'a>> {
+        let (fields, variants): (&[Field<'a>], &[Variant<'a>]) = match self {
+            Data::Struct(_, fields) => (fields, &[]),
+            Data::Enum(variants) => (&[], variants),
+        };
+        fields
+            .iter()
+            .chain(variants.iter().flat_map(|variant| variant.fields.iter()))
+    }
+}
+
+impl<'a> Container<'a> {
+    /// See [`get_path`].
+    pub fn attr(&self, path: &str) -> syn::Result<&Val> {
+        get_path(&self.attrs, path, self.ident.span())
+    }
+
+    /// The field named `name` (or index of a tuple field), searched by [`Data::all_fields`].
+    pub fn field(&self, name: &str) -> syn::Result<&Field<'a>> {
+        self.data
+            .all_fields()
+            .find(|field| field.name() == name)
+            .ok_or_else(|| {
+                let msg = format!("no field `{name}` in `{}` #Container.field", self.ident);
+                syn::Error::new(self.ident.span(), msg)
+            })
+    }
+
+    /// `cont.field_attr("sales", "flow.from")`
+    pub fn field_attr(&self, name: &str, path: &str) -> syn::Result<&Val> {
+        self.field(name)?.attr(path).map_err(|err| {
+            syn::Error::new(err.span(), format!("{err} of field `{name}`"))
+        })
+    }
+
+    /// The fields having the top level `key`, eg. `cont.fields_with("stock")`.
+    pub fn fields_with<'s>(&'s self, key: &'s str) -> impl Iterator<Item = &'s Field<'a>> + 's {
+        self.data
+            .all_fields()
+            .filter(move |field| field.attrs.contains_key(key))
+    }
+}
+
+impl<'a> Variant<'a> {
+    /// See [`get_path`].
+    pub fn attr(&self, path: &str) -> syn::Result<&Val> {
+        get_path(&self.attrs, path, self.ident.span())
+    }
+
+    /// `attrs` over the inherited container defaults, with the level each val comes from.
+    pub fn effective_attrs(&self) -> HashMap<&str, (AttrLevel, &Val)> {
+        effective_attrs(&self.inherited, &self.attrs, AttrLevel::Variant)
+    }
+}
+
+fn effective_attrs<'v>(
+    inherited: &'v HashMap<String, Inherited>,
+    attrs: &'v HashMap<String, Val>,
+    level: AttrLevel,
+) -> HashMap<&'v str, (AttrLevel, &'v Val)> {
+    let mut effective: HashMap<_, _> = inherited
+        .iter()
+        .map(|(key, inherited)| (key.as_str(), (inherited.level, &inherited.val)))
+        .collect();
+    effective.extend(attrs.iter().map(|(key, val)| (key.as_str(), (level, val))));
+    effective
+}
+
+impl<'a> Field<'a> {
+    /// The field ident, or the index of a tuple field.
+    pub fn name(&self) -> String {
+        match &self.member {
+            syn::Member::Named(ident) => ident.to_string(),
+            syn::Member::Unnamed(index) => index.index.to_string(),
+        }
+    }
+
+    /// See [`get_path`].
+    pub fn attr(&self, path: &str) -> syn::Result<&Val> {
+        get_path(&self.attrs, path, syn::spanned::Spanned::span(&self.member))
+    }
+
+    /// `attrs` over the inherited variant and container defaults, with the level each val
+    /// comes from.
+    ///
+    /// With `Options::inherit` of `ode_solver`, `#[sim(ode_solver = "rk4")]` on the struct and
+    /// `#[sim(ode_solver = "eula")]` on a field, the field sees ("eula", AttrLevel::Field),
+    /// the other fields ("rk4", AttrLevel::Container).
+    pub fn effective_attrs(&self) -> HashMap<&str, (AttrLevel, &Val)> {
+        effective_attrs(&self.inherited, &self.attrs, AttrLevel::Field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::internals::ast::Symbol;
+    use crate::internals::ctxt::Ctxt;
+    use crate::internals::parse::from_ast;
+
+    use super::*;
+
+    #[test]
+    fn dotted_paths() {
+        let input: syn::DeriveInput = syn::parse_quote! {
+            #[fsm(trans(to = "B"))]
+            #[fsm(trans(to = "C", when(event = "go")), name = "fsm")]
+            enum Fsm {
+                A {
+                    #[fsm(flow(from = "a", to = "b"))]
+                    sales: f64,
+                },
+                B(#[fsm(stock)] f64, u8),
+            }
+        };
+        let cx = Ctxt::new();
+        let cont = from_ast(&cx, &input, Symbol("fsm")).unwrap();
+        cx.check().unwrap();
+
+        assert_eq!(cont.attr("name").unwrap().as_str().unwrap(), "fsm");
+        assert_eq!(cont.attr("trans.0.to").unwrap().as_str().unwrap(), "B");
+        assert_eq!(cont.attr("trans.1.when.event").unwrap().as_str().unwrap(), "go");
+        assert_eq!(cont.attr("trans").unwrap().as_slice().len(), 2);
+        assert_eq!(cont.attr("name").unwrap().as_slice().len(), 1);
+        assert!(cont.attr("trans.1.when").unwrap().as_map().unwrap().contains_key("event"));
+        assert_eq!(cont.field_attr("sales", "flow.to").unwrap().as_str().unwrap(), "b");
+
+        let err = |res: syn::Result<&Val>| res.unwrap_err().to_string();
+        assert_eq!(err(cont.attr("state")), "missing `state`, no `state` in attrs #get_path");
+        assert_eq!(
+            err(cont.attr("trans.2.to")),
+            "missing `trans.2.to`, no `2` in `trans` #Val.get_path"
+        );
+        assert_eq!(
+            err(cont.attr("trans.1.when.state")),
+            "missing `trans.1.when.state`, no `state` in `trans.1.when` #Val.get_path"
+        );
+        assert_eq!(
+            err(cont.field_attr("sales", "flow.val")),
+            "missing `flow.val`, no `val` in `flow` #Val.get_path of field `sales`"
+        );
+        assert_eq!(
+            err(cont.field_attr("clients", "stock")),
+            "no field `clients` in `Fsm` #Container.field"
+        );
+    }
+
+    #[test]
+    fn fields_across_variants() {
+        let input: syn::DeriveInput = syn::parse_quote! {
+            enum Fsm {
+                A {
+                    #[fsm(stock)]
+                    a: f64,
+                    b: f64,
+                },
+                B,
+                C(#[fsm(stock)] f64, u8),
+            }
+        };
+        let cx = Ctxt::new();
+        let cont = from_ast(&cx, &input, Symbol("fsm")).unwrap();
+        cx.check().unwrap();
+
+        let names: Vec<_> = cont.data.all_fields().map(Field::name).collect();
+        assert_eq!(names, ["a", "b", "0", "1"]);
+        let stocks: Vec<_> = cont.fields_with("stock").map(Field::name).collect();
+        assert_eq!(stocks, ["a", "0"]);
+        assert!(cont.field("1").unwrap().attrs.is_empty());
+    }
+}
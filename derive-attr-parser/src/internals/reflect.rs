@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use proc_macro2::{Literal, Span, TokenStream};
+use quote::{quote, quote_spanned, ToTokens};
+
+use crate::internals::ast::{Container, Data, Field, Val, Variant};
+
+// The tokens of `impl derive_attr_runtime::AttrReflect`, the derive decides the runtime path.
+// #[demo(stock(val = "total_population"))] clients: f64
+// => FieldMeta { name: "clients", ty: "f64", attrs: &[("stock", StaticVal::Map(&[("val", StaticVal::Str("total_population"))]))] }
+
+/// `const _: () = assert!(..)` over the `AttrReflect::META` of another type.
+///
+/// This is how a derive sees the attrs of the types it refers to: `cond` reads the meta as
+/// `meta`, a failing check stops the build with `msg` at `span`. `ty` has to be concrete, a
+/// const item can't use the generics around it.
+/// ```ignore
+/// // on BassDiffusion { #[demo(output(from = "total"))] population: Population }
+/// reflect_assert(&runtime, &field.ty, quote!(meta.any_field_has_str(&["output", "to"], "total")),
+///     "`Population` has no output `total`", span);
+/// ```
+pub fn reflect_assert(
+    runtime: &syn::Path,
+    ty: &syn::Type,
+    cond: TokenStream,
+    msg: &str,
+    span: Span,
+) -> TokenStream {
+    quote_spanned! {span=>
+        const _: () = {
+            let meta = <#ty as #runtime::AttrReflect>::META;
+            assert!(#cond, "{}", #msg);
+        };
+    }
+}
+
+impl<'a> Container<'a> {
+    /// `impl #runtime::AttrReflect for #ident`, with `attrs` of every level as `'static` data.
+    ///
+    /// `runtime` is the path of the `derive-attr-runtime` crate, see [`Container::crate_path`].
+    pub fn reflect_tokens(&self, runtime: &syn::Path) -> TokenStream {
+        let meta = self.meta_tokens(runtime);
+        let trait_path = syn::parse_quote!(#runtime::AttrReflect);
+        self.impl_trait(
+            &trait_path,
+            &[],
+            quote! {
+                const META: #runtime::ContainerMeta = #meta;
+            },
+        )
+    }
+
+    fn meta_tokens(&self, runtime: &syn::Path) -> TokenStream {
+        let ident = self.ident.to_string();
+        let attrs = attrs_tokens(&self.attrs, runtime);
+        let (fields, variants) = match &self.data {
+            Data::Struct(_, fields) => (fields_tokens(fields, runtime), quote!(&[])),
+            Data::Enum(variants) => {
+                let variants = variants.iter().map(|variant| variant_tokens(variant, runtime));
+                (quote!(&[]), quote!(&[#(#variants),*]))
+            }
+        };
+        quote! {
+            #runtime::ContainerMeta {
+                ident: #ident,
+                attrs: #attrs,
+                fields: #fields,
+                variants: #variants,
+            }
+        }
+    }
+}
+
+fn variant_tokens(variant: &Variant, runtime: &syn::Path) -> TokenStream {
+    let ident = variant.ident.to_string();
+    let attrs = attrs_tokens(&variant.attrs, runtime);
+    let fields = fields_tokens(&variant.fields, runtime);
+    quote! {
+        #runtime::VariantMeta {
+            ident: #ident,
+            attrs: #attrs,
+            fields: #fields,
+        }
+    }
+}
+
+fn fields_tokens(fields: &[Field], runtime: &syn::Path) -> TokenStream {
+    let fields = fields.iter().map(|field| {
+        let name = field.name();
+        let ty = field.ty.to_token_stream().to_string();
+        let attrs = attrs_tokens(&field.attrs, runtime);
+        quote! {
+            #runtime::FieldMeta {
+                name: #name,
+                ty: #ty,
+                attrs: #attrs,
+            }
+        }
+    });
+    quote!(&[#(#fields),*])
+}
+
+// Sorted by key, the same input always expands the same.
+fn attrs_tokens(attrs: &HashMap<String, Val>, runtime: &syn::Path) -> TokenStream {
+    let mut keys: Vec<_> = attrs.keys().collect();
+    keys.sort();
+    let pairs = keys.into_iter().map(|key| {
+        let val = val_tokens(&attrs[key], runtime);
+        quote!((#key, #val))
+    });
+    quote!(&[#(#pairs),*])
+}
+
+fn val_tokens(val: &Val, runtime: &syn::Path) -> TokenStream {
+    match val {
+        Val::Empty => quote!(#runtime::StaticVal::Empty),
+        Val::Str(s) => quote!(#runtime::StaticVal::Str(#s)),
+        Val::Bool(b) => quote!(#runtime::StaticVal::Bool(#b)),
+        Val::Int(i) => quote!(#runtime::StaticVal::Int(#i)),
+        Val::Float(f) => {
+            let f = if f.is_finite() {
+                Literal::f64_suffixed(*f).into_token_stream()
+            } else if f.is_nan() {
+                quote!(f64::NAN)
+            } else if *f > 0.0 {
+                quote!(f64::INFINITY)
+            } else {
+                quote!(f64::NEG_INFINITY)
+            };
+            quote!(#runtime::StaticVal::Float(#f))
+        }
+        Val::Map(map) => {
+            let attrs = attrs_tokens(map, runtime);
+            quote!(#runtime::StaticVal::Map(#attrs))
+        }
+        Val::Vec(vs) => {
+            let vs = vs.iter().map(|val| val_tokens(val, runtime));
+            quote!(#runtime::StaticVal::Vec(&[#(#vs),*]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::internals::ast::Symbol;
+    use crate::internals::ctxt::Ctxt;
+    use crate::internals::parse::{from_ast_with, Options};
+
+    use super::*;
+
+    fn reflect_of(input: &syn::DeriveInput) -> String {
+        let opts = Options {
+            embedded: true,
+            ..Options::default()
+        };
+        let cx = Ctxt::new();
+        let cont = from_ast_with(&cx, input, Symbol("demo"), &opts).unwrap();
+        cx.check().unwrap();
+        cont.reflect_tokens(&syn::parse_quote!(rt)).to_string()
+    }
+
+    #[test]
+    fn struct_meta_is_sorted_static_data() {
+        let input = syn::parse_quote! {
+            #[demo(name = "bass", algos = r#"{k: 8, on: true, dt: 0.5, xs: [1, "a"]}"#)]
+            struct Bass<T> {
+                #[demo(stock(val = "total"), output)]
+                clients: Vec<T>,
+                other: f64,
+            }
+        };
+        let expected = quote! {
+            #[automatically_derived]
+            impl<T> rt::AttrReflect for Bass<T> {
+                const META: rt::ContainerMeta = rt::ContainerMeta {
+                    ident: "Bass",
+                    attrs: &[
+                        ("algos", rt::StaticVal::Map(&[
+                            ("dt", rt::StaticVal::Float(0.5f64)),
+                            ("k", rt::StaticVal::Int(8i64)),
+                            ("on", rt::StaticVal::Bool(true)),
+                            ("xs", rt::StaticVal::Vec(&[rt::StaticVal::Int(1i64), rt::StaticVal::Str("a")]))
+                        ])),
+                        ("name", rt::StaticVal::Str("bass"))
+                    ],
+                    fields: &[
+                        rt::FieldMeta {
+                            name: "clients",
+                            ty: "Vec < T >",
+                            attrs: &[
+                                ("output", rt::StaticVal::Empty),
+                                ("stock", rt::StaticVal::Map(&[("val", rt::StaticVal::Str("total"))]))
+                            ],
+                        },
+                        rt::FieldMeta { name: "other", ty: "f64", attrs: &[], }
+                    ],
+                    variants: &[],
+                };
+            }
+        };
+        assert_eq!(reflect_of(&input), expected.to_string());
+    }
+
+    #[test]
+    fn enum_meta_has_variants() {
+        let input = syn::parse_quote! {
+            #[demo(start = "A")]
+            enum Fsm {
+                #[demo(trans(to = "B"))]
+                A,
+                B(#[demo(stock)] f64),
+            }
+        };
+        let expected = quote! {
+            #[automatically_derived]
+            impl rt::AttrReflect for Fsm {
+                const META: rt::ContainerMeta = rt::ContainerMeta {
+                    ident: "Fsm",
+                    attrs: &[("start", rt::StaticVal::Str("A"))],
+                    fields: &[],
+                    variants: &[
+                        rt::VariantMeta {
+                            ident: "A",
+                            attrs: &[("trans", rt::StaticVal::Map(&[("to", rt::StaticVal::Str("B"))]))],
+                            fields: &[],
+                        },
+                        rt::VariantMeta {
+                            ident: "B",
+                            attrs: &[],
+                            fields: &[rt::FieldMeta { name: "0", ty: "f64", attrs: &[("stock", rt::StaticVal::Empty)], }],
+                        }
+                    ],
+                };
+            }
+        };
+        assert_eq!(reflect_of(&input), expected.to_string());
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::spanned::Spanned;
+
+use crate::internals::ast::{AttrEntry, Container, Data, Val};
+use crate::internals::ctxt::Ctxt;
+use crate::internals::literal::{is_embedded, parse_embedded};
+use crate::internals::parse::Options;
+
+// Placeholders in Val::Str, see Options::interpolate.
+// {ident}    the struct or enum name
+// {variant}  the variant name, on a variant and its fields
+// {field}    the field name, or the index of a tuple field
+// {env:VAR}  the env var VAR when the macro expands, see Container::env_tokens
+// `{{` and `}}` are literal braces. Any other name in braces, like `{idnet}`, is an error.
+// Braces around anything else are left as written, so embedded literals like r#"{key:"val"}"#
+// keep working. With Options::embedded, a string with placeholders is parsed as embedded
+// literal after they resolve, r#"{name: "{ident}"}"# on Bass into {"name": Val::Str("Bass")}.
+// #[sim(input_name = "{ident}Input")] on Bass parse into {"input_name": Val::Str("BassInput")}
+
+/// The names placeholders resolve to on one node.
+struct Scope<'s> {
+    ident: &'s str,
+    variant: Option<&'s str>,
+    field: Option<&'s str>,
+}
+
+/// Resolve the placeholders of the container, its variants and fields.
+pub(crate) fn interpolate_attrs(cx: &Ctxt, opts: &Options, cont: &mut Container) {
+    let ident = cont.ident.to_string();
+    let scope = Scope {
+        ident: &ident,
+        variant: None,
+        field: None,
+    };
+    let mut envs = vec![];
+    let span = cont.ident.span();
+    interpolate_node(cx, opts, &scope, span, &mut cont.attrs, &mut cont.entries, &mut envs);
+    match &mut cont.data {
+        Data::Enum(variants) => {
+            for variant in variants {
+                let name = variant.ident.to_string();
+                let scope = Scope {
+                    variant: Some(&name),
+                    ..scope
+                };
+                let span = variant.ident.span();
+                interpolate_node(cx, opts, &scope, span, &mut variant.attrs, &mut variant.entries, &mut envs);
+                for field in &mut variant.fields {
+                    let name = field.name();
+                    let scope = Scope {
+                        field: Some(&name),
+                        ..scope
+                    };
+                    let span = field.member.span();
+                    interpolate_node(cx, opts, &scope, span, &mut field.attrs, &mut field.entries, &mut envs);
+                }
+            }
+        }
+        Data::Struct(_, fields) => {
+            for field in fields {
+                let name = field.name();
+                let scope = Scope {
+                    field: Some(&name),
+                    ..scope
+                };
+                let span = field.member.span();
+                interpolate_node(cx, opts, &scope, span, &mut field.attrs, &mut field.entries, &mut envs);
+            }
+        }
+    }
+    envs.sort();
+    envs.dedup();
+    cont.envs = envs;
+}
+
+fn interpolate_node(
+    cx: &Ctxt,
+    opts: &Options,
+    scope: &Scope,
+    span: Span,
+    attrs: &mut HashMap<String, Val>,
+    entries: &mut [AttrEntry],
+    envs: &mut Vec<String>,
+) {
+    let mut errors = vec![];
+    for val in attrs.values_mut() {
+        interpolate_val(opts, scope, span, val, &mut errors, envs);
+    }
+    for err in errors {
+        cx.syn_error(err);
+    }
+    // the same errors and env vars again, reported once from attrs.
+    for entry in entries {
+        interpolate_val(opts, scope, entry.span, &mut entry.val, &mut vec![], &mut vec![]);
+    }
+}
+
+fn interpolate_val(
+    opts: &Options,
+    scope: &Scope,
+    span: Span,
+    val: &mut Val,
+    errors: &mut Vec<syn::Error>,
+    envs: &mut Vec<String>,
+) {
+    match val {
+        Val::Str(s) => match interpolate(s, scope, envs) {
+            // the embedded literal parse_attrs left for after the placeholders.
+            Ok(resolved) if opts.embedded && has_placeholder(s) && is_embedded(&resolved) => {
+                match parse_embedded(&syn::LitStr::new(&resolved, span)) {
+                    Ok(parsed) => *val = parsed,
+                    Err(err) => errors.push(err),
+                }
+            }
+            Ok(resolved) => *s = resolved,
+            Err(msg) => errors.push(syn::Error::new(span, format!("{msg} in {s:?} #interpolate"))),
+        },
+        Val::Map(map) => {
+            for val in map.values_mut() {
+                interpolate_val(opts, scope, span, val, errors, envs);
+            }
+        }
+        Val::Vec(vs) => {
+            for val in vs {
+                interpolate_val(opts, scope, span, val, errors, envs);
+            }
+        }
+        Val::Empty | Val::Bool(_) | Val::Int(_) | Val::Float(_) => {}
+    }
+}
+
+fn interpolate(s: &str, scope: &Scope, envs: &mut Vec<String>) -> Result<String, String> {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(at) = rest.find(['{', '}']) {
+        out.push_str(&rest[..at]);
+        rest = &rest[at..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            out.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        match braced(rest) {
+            Some(name) if is_placeholder(name) => {
+                out.push_str(&resolve(name, scope, envs)?);
+                rest = &rest[name.len() + 2..];
+            }
+            Some(name) if is_name(name) => return Err(unknown(name)),
+            _ => {
+                out.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+// The names in braces of `s`, `{{` and `}}` skipped.
+fn names(s: &str) -> impl Iterator<Item = &str> {
+    let mut rest = s;
+    std::iter::from_fn(move || {
+        while let Some(at) = rest.find(['{', '}']) {
+            rest = &rest[at..];
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                rest = &rest[2..];
+                continue;
+            }
+            let name = braced(rest);
+            rest = &rest[1..];
+            if name.is_some() {
+                return name;
+            }
+        }
+        None
+    })
+}
+
+// `name` of `{name}..`, up to the first `}`.
+fn braced(s: &str) -> Option<&str> {
+    s.strip_prefix('{')
+        .and_then(|tail| tail.find('}').map(|end| &tail[..end]))
+}
+
+// Only the names above, `env:` with a var name made of `_` and alphanumerics.
+fn is_placeholder(name: &str) -> bool {
+    match name.strip_prefix("env:") {
+        Some(var) => !var.is_empty() && var.chars().all(|c| c == '_' || c.is_ascii_alphanumeric()),
+        None => matches!(name, "ident" | "variant" | "field"),
+    }
+}
+
+// An ident, that is a misspelled placeholder rather than literal braces.
+fn is_name(name: &str) -> bool {
+    name.starts_with(|c: char| c == '_' || c.is_alphabetic())
+        && name.chars().all(|c| c == '_' || c.is_alphanumeric())
+}
+
+fn unknown(name: &str) -> String {
+    format!("unknown placeholder `{{{name}}}`, expect {{ident}}, {{variant}}, {{field}} or {{env:VAR}}")
+}
+
+/// Whether `s` has a placeholder to resolve.
+pub(crate) fn has_placeholder(s: &str) -> bool {
+    names(s).any(is_placeholder)
+}
+
+/// The error of the first unknown placeholder in `s`, as `interpolate` reports it.
+pub(crate) fn check_placeholders(s: &str) -> Result<(), String> {
+    match names(s).find(|name| !is_placeholder(name) && is_name(name)) {
+        Some(name) => Err(format!("{} in {s:?} #interpolate", unknown(name))),
+        None => Ok(()),
+    }
+}
+
+fn resolve(name: &str, scope: &Scope, envs: &mut Vec<String>) -> Result<String, String> {
+    if let Some(var) = name.strip_prefix("env:") {
+        envs.push(var.to_string());
+        return std::env::var(var).map_err(|_| format!("env var `{var}` is not set"));
+    }
+    let found = match name {
+        "ident" => Some(scope.ident),
+        "variant" => scope.variant,
+        _ => scope.field,
+    };
+    found
+        .map(str::to_string)
+        .ok_or_else(|| format!("`{{{name}}}` is not available here"))
+}
+
+impl<'a> Container<'a> {
+    /// `option_env!` of every env var a `{env:VAR}` read, put it in the expansion so cargo
+    /// rebuilds when they change.
+    pub fn env_tokens(&self) -> TokenStream {
+        let vars = &self.envs;
+        quote! {
+            #(const _: ::core::option::Option<&str> = ::core::option_env!(#vars);)*
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_known_names_are_placeholders() {
+        let scope = Scope {
+            ident: "Bass",
+            variant: None,
+            field: Some("clients"),
+        };
+        let mut envs = vec![];
+        let cases = [
+            ("{ident}Input", "BassInput"),
+            ("{field}_{ident}", "clients_Bass"),
+            (r#"{algo: "eula", steps: 10}"#, r#"{algo: "eula", steps: 10}"#),
+            ("{algo:eula}", "{algo:eula}"),
+            ("[{ident}, {}]", "[Bass, {}]"),
+            ("{{ident}} {", "{ident} {"),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(interpolate(s, &scope, &mut envs).as_deref(), Ok(expected), "{s:?}");
+        }
+        assert!(envs.is_empty());
+        let err = interpolate("{variant}", &scope, &mut envs).unwrap_err();
+        assert_eq!(err, "`{variant}` is not available here");
+        let err = interpolate("{idnet}Input", &scope, &mut envs).unwrap_err();
+        assert_eq!(
+            err,
+            "unknown placeholder `{idnet}`, expect {ident}, {variant}, {field} or {env:VAR}"
+        );
+    }
+
+    #[test]
+    fn env_vars_are_recorded() {
+        let scope = Scope {
+            ident: "Bass",
+            variant: None,
+            field: None,
+        };
+        let mut envs = vec![];
+        let resolved = interpolate("{env:CARGO_PKG_NAME}", &scope, &mut envs);
+        assert_eq!(resolved.as_deref(), Ok(env!("CARGO_PKG_NAME")));
+        let err = interpolate("{env:DERIVE_ATTR_PARSER_UNSET}", &scope, &mut envs).unwrap_err();
+        assert_eq!(err, "env var `DERIVE_ATTR_PARSER_UNSET` is not set");
+        assert_eq!(envs, ["CARGO_PKG_NAME", "DERIVE_ATTR_PARSER_UNSET"]);
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::HashSet;
+
+use syn::visit::Visit;
+use syn::{GenericArgument, PathArguments, Type};
+
+use crate::internals::ast::Field;
+
+// Helpers over `Field::ty`, types are matched by the last path segment, so `Option<T>`,
+// `std::option::Option<T>` and `core::option::Option<T>` are all options, a type alias is not.
+
+/// The std collection a field type is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollectionKind {
+    Vec,
+    VecDeque,
+    LinkedList,
+    BinaryHeap,
+    HashSet,
+    BTreeSet,
+    HashMap,
+    BTreeMap,
+    /// `[T]`, `&[T]`, `Box<[T]>` ...
+    Slice,
+    /// `[T; N]`
+    Array,
+}
+
+impl CollectionKind {
+    /// `HashMap` and `BTreeMap`, having key and value types.
+    pub fn is_map(self) -> bool {
+        matches!(self, CollectionKind::HashMap | CollectionKind::BTreeMap)
+    }
+}
+
+const COLLECTIONS: [(&str, CollectionKind); 8] = [
+    ("Vec", CollectionKind::Vec),
+    ("VecDeque", CollectionKind::VecDeque),
+    ("LinkedList", CollectionKind::LinkedList),
+    ("BinaryHeap", CollectionKind::BinaryHeap),
+    ("HashSet", CollectionKind::HashSet),
+    ("BTreeSet", CollectionKind::BTreeSet),
+    ("HashMap", CollectionKind::HashMap),
+    ("BTreeMap", CollectionKind::BTreeMap),
+];
+
+impl<'a> Field<'a> {
+    /// `T` of `Option<T>`.
+    pub fn option_inner(&self) -> Option<&'a Type> {
+        single_arg(self.ty, "Option")
+    }
+
+    /// `T` of `Box<T>`.
+    pub fn box_inner(&self) -> Option<&'a Type> {
+        single_arg(self.ty, "Box")
+    }
+
+    /// `&T` or `&mut T`.
+    pub fn reference(&self) -> Option<&'a syn::TypeReference> {
+        match ungroup(self.ty) {
+            Type::Reference(reference) => Some(reference),
+            _ => None,
+        }
+    }
+
+    /// `PhantomData<T>`.
+    pub fn is_phantom(&self) -> bool {
+        path_args(self.ty, "PhantomData").is_some()
+    }
+
+    /// The collection the field is, looking through references, eg. `&[T]` is a slice.
+    pub fn collection_kind(&self) -> Option<CollectionKind> {
+        collection(self.ty).map(|(kind, _)| kind)
+    }
+
+    /// The item types of the collection, `[K, V]` for maps, `[T]` for the others.
+    pub fn collection_items(&self) -> Vec<&'a Type> {
+        collection(self.ty).map(|(_, items)| items).unwrap_or_default()
+    }
+
+    /// The params of `generics` the field type mentions, eg. `T` and `'a` in `&'a [T]`.
+    pub fn generic_params_used<'g>(&self, generics: &'g syn::Generics) -> Vec<&'g syn::GenericParam> {
+        let mut used = UsedParams::default();
+        used.visit_type(self.ty);
+        generics
+            .params
+            .iter()
+            .filter(|param| match param {
+                syn::GenericParam::Type(param) => used.idents.contains(&param.ident),
+                syn::GenericParam::Const(param) => used.idents.contains(&param.ident),
+                syn::GenericParam::Lifetime(param) => used.lifetimes.contains(&param.lifetime),
+            })
+            .collect()
+    }
+}
+
+fn ungroup(mut ty: &Type) -> &Type {
+    loop {
+        match ty {
+            Type::Group(group) => ty = &group.elem,
+            Type::Paren(paren) => ty = &paren.elem,
+            _ => return ty,
+        }
+    }
+}
+
+// The generic args of `ty` when the last segment of its path is `name`.
+fn path_args<'t>(ty: &'t Type, name: &str) -> Option<Vec<&'t Type>> {
+    let path = match ungroup(ty) {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        _ => return None,
+    };
+    let seg = path.segments.last()?;
+    if seg.ident != name {
+        return None;
+    }
+    let args = match &seg.arguments {
+        PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    };
+    Some(args)
+}
+
+fn single_arg<'t>(ty: &'t Type, name: &str) -> Option<&'t Type> {
+    match path_args(ty, name)?.as_slice() {
+        [arg] => Some(arg),
+        _ => None,
+    }
+}
+
+fn collection(ty: &Type) -> Option<(CollectionKind, Vec<&Type>)> {
+    match ungroup(ty) {
+        Type::Slice(slice) => Some((CollectionKind::Slice, vec![&slice.elem])),
+        Type::Array(array) => Some((CollectionKind::Array, vec![&array.elem])),
+        Type::Reference(reference) => collection(&reference.elem),
+        ty => {
+            if let Some(inner) = single_arg(ty, "Box") {
+                if let Type::Slice(slice) = ungroup(inner) {
+                    return Some((CollectionKind::Slice, vec![&slice.elem]));
+                }
+            }
+            COLLECTIONS.iter().find_map(|(name, kind)| {
+                let mut args = path_args(ty, name)?;
+                // drop the hasher of HashMap<K, V, S>, HashSet<T, S>.
+                args.truncate(if kind.is_map() { 2 } else { 1 });
+                Some((*kind, args))
+            })
+        }
+    }
+}
+
+#[derive(Default)]
+struct UsedParams {
+    idents: HashSet<syn::Ident>,
+    lifetimes: HashSet<syn::Lifetime>,
+}
+
+impl<'ast> Visit<'ast> for UsedParams {
+    // `T`, `T::Assoc` and `N` in `[u8; N]`.
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        if path.leading_colon.is_none() {
+            if let Some(first) = path.segments.first() {
+                self.idents.insert(first.ident.clone());
+            }
+        }
+        syn::visit::visit_path(self, path);
+    }
+
+    fn visit_lifetime(&mut self, lifetime: &'ast syn::Lifetime) {
+        self.lifetimes.insert(lifetime.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::ToTokens;
+
+    use crate::internals::ast::{Container, Symbol};
+    use crate::internals::ctxt::Ctxt;
+    use crate::internals::parse::from_ast;
+
+    use super::*;
+
+    fn with_fields(input: &syn::DeriveInput, f: impl FnOnce(&Container)) {
+        let cx = Ctxt::new();
+        let cont = from_ast(&cx, input, Symbol("sim")).unwrap();
+        cx.check().unwrap();
+        f(&cont);
+    }
+
+    fn text(ty: Option<&Type>) -> Option<String> {
+        ty.map(|ty| ty.to_token_stream().to_string())
+    }
+
+    #[test]
+    fn options() {
+        let input = syn::parse_quote! {
+            struct A {
+                a: Option<u8>,
+                b: std::option::Option<Vec<u8>>,
+                c: ::core::option::Option<&'static str>,
+                d: MaybeU8,
+                e: <Self as Trait>::Option,
+                f: Box<f64>,
+            }
+        };
+        with_fields(&input, |cont| {
+            let inner = |name: &str| text(cont.field(name).unwrap().option_inner());
+            assert_eq!(inner("a").as_deref(), Some("u8"));
+            assert_eq!(inner("b").as_deref(), Some("Vec < u8 >"));
+            assert_eq!(inner("c").as_deref(), Some("& 'static str"));
+            // an alias or a qualified path is no option.
+            assert_eq!(inner("d"), None);
+            assert_eq!(inner("e"), None);
+            assert_eq!(inner("f"), None);
+            assert_eq!(text(cont.field("f").unwrap().box_inner()).as_deref(), Some("f64"));
+        });
+    }
+
+    #[test]
+    fn collections() {
+        let input = syn::parse_quote! {
+            struct A<'a> {
+                a: Vec<u8>,
+                b: std::collections::HashMap<String, f64, RandomState>,
+                c: &'a [u8],
+                d: Box<[u8]>,
+                e: [u8; 4],
+                f: HashSet<u8, S>,
+                g: u8,
+            }
+        };
+        with_fields(&input, |cont| {
+            let kind = |name: &str| cont.field(name).unwrap().collection_kind();
+            let items = |name: &str| {
+                let items = cont.field(name).unwrap().collection_items();
+                items.iter().map(|ty| ty.to_token_stream().to_string()).collect::<Vec<_>>()
+            };
+            assert_eq!(kind("a"), Some(CollectionKind::Vec));
+            assert_eq!(items("a"), ["u8"]);
+            assert_eq!(kind("b"), Some(CollectionKind::HashMap));
+            assert!(CollectionKind::HashMap.is_map());
+            assert_eq!(items("b"), ["String", "f64"]);
+            assert_eq!(kind("c"), Some(CollectionKind::Slice));
+            assert_eq!(kind("d"), Some(CollectionKind::Slice));
+            assert_eq!(kind("e"), Some(CollectionKind::Array));
+            assert_eq!(kind("f"), Some(CollectionKind::HashSet));
+            assert_eq!(items("f"), ["u8"]);
+            assert_eq!(kind("g"), None);
+            assert!(items("g").is_empty());
+        });
+    }
+
+    #[test]
+    fn generic_params() {
+        let input = syn::parse_quote! {
+            struct A<'a, 'b, T: Trait, U, const N: usize> {
+                a: &'a [T; N],
+                b: Vec<Option<T::Assoc>>,
+                c: std::marker::PhantomData<(U, &'b ())>,
+                d: ::T,
+                e: u8,
+            }
+        };
+        with_fields(&input, |cont| {
+            let used = |name: &str| {
+                let field = cont.field(name).unwrap();
+                let params = field.generic_params_used(cont.generics);
+                params.iter().map(|param| param.to_token_stream().to_string()).collect::<Vec<_>>()
+            };
+            assert_eq!(used("a"), ["'a", "T : Trait", "const N : usize"]);
+            // only through a nested path.
+            assert_eq!(used("b"), ["T : Trait"]);
+            assert_eq!(used("c"), ["'b", "U"]);
+            assert!(cont.field("c").unwrap().is_phantom());
+            // `::T` is a crate, not the param.
+            assert!(used("d").is_empty());
+            assert!(used("e").is_empty());
+        });
+    }
+}
@@ -0,0 +1,165 @@
+//! Read-only traversal of a parsed [`Container`], modeled on `syn::visit`.
+//!
+//! Override the `visit_*` methods of interest and call the free function of the same name to
+//! keep walking the children. Every `Val::Map`, top level `attrs` included, goes through
+//! [`Visit::visit_attrs`]. `cfgs` and `entries` are not visited, nor `inherited` of variants and
+//! fields: it copies the vals of the container and variant, which are visited there.
+//!
+//! ```
+//! use derive_attr_parser::visit::{self, Visit};
+//! use derive_attr_parser::Val;
+//!
+//! /// Collect the `val = "..."` of any depth.
+//! struct Vals(Vec<String>);
+//!
+//! impl<'ast> Visit<'ast> for Vals {
+//!     fn visit_attr(&mut self, key: &'ast str, val: &'ast Val) {
+//!         if let (Val::Str(s), "val") = (val, key) {
+//!             self.0.push(s.clone());
+//!         }
+//!         visit::visit_attr(self, key, val);
+//!     }
+//! }
+//! ```
+use std::collections::HashMap;
+
+use crate::internals::ast::{Container, Data, Field, Val, Variant};
+
+pub trait Visit<'ast> {
+    fn visit_container(&mut self, node: &'ast Container<'_>) {
+        visit_container(self, node)
+    }
+    fn visit_data(&mut self, node: &'ast Data<'_>) {
+        visit_data(self, node)
+    }
+    fn visit_variant(&mut self, node: &'ast Variant<'_>) {
+        visit_variant(self, node)
+    }
+    fn visit_field(&mut self, node: &'ast Field<'_>) {
+        visit_field(self, node)
+    }
+    fn visit_attrs(&mut self, node: &'ast HashMap<String, Val>) {
+        visit_attrs(self, node)
+    }
+    fn visit_attr(&mut self, key: &'ast str, val: &'ast Val) {
+        visit_attr(self, key, val)
+    }
+    fn visit_val(&mut self, node: &'ast Val) {
+        visit_val(self, node)
+    }
+}
+
+pub fn visit_container<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Container<'_>) {
+    v.visit_attrs(&node.attrs);
+    v.visit_data(&node.data);
+}
+
+pub fn visit_data<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Data<'_>) {
+    match node {
+        Data::Enum(variants) => {
+            for variant in variants {
+                v.visit_variant(variant);
+            }
+        }
+        Data::Struct(_, fields) => {
+            for field in fields {
+                v.visit_field(field);
+            }
+        }
+    }
+}
+
+pub fn visit_variant<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Variant<'_>) {
+    v.visit_attrs(&node.attrs);
+    for field in &node.fields {
+        v.visit_field(field);
+    }
+}
+
+pub fn visit_field<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Field<'_>) {
+    v.visit_attrs(&node.attrs);
+}
+
+pub fn visit_attrs<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast HashMap<String, Val>) {
+    for (key, val) in node {
+        v.visit_attr(key, val);
+    }
+}
+
+pub fn visit_attr<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, _key: &'ast str, val: &'ast Val) {
+    v.visit_val(val);
+}
+
+pub fn visit_val<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, node: &'ast Val) {
+    match node {
+        Val::Empty | Val::Str(_) | Val::Bool(_) | Val::Int(_) | Val::Float(_) => {}
+        Val::Map(map) => v.visit_attrs(map),
+        Val::Vec(vs) => {
+            for val in vs {
+                v.visit_val(val);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::internals::ast::Symbol;
+    use crate::internals::ctxt::Ctxt;
+    use crate::internals::parse::{from_ast_with, Options};
+
+    use super::*;
+
+    // `key=str` of any depth, and the fields seen.
+    #[derive(Default)]
+    struct Collect {
+        strs: Vec<String>,
+        fields: Vec<String>,
+    }
+
+    impl<'ast> Visit<'ast> for Collect {
+        fn visit_field(&mut self, node: &'ast Field<'_>) {
+            self.fields.push(node.name());
+            visit_field(self, node);
+        }
+        fn visit_attr(&mut self, key: &'ast str, val: &'ast Val) {
+            if let Val::Str(s) = val {
+                self.strs.push(format!("{key}={s}"));
+            }
+            visit_attr(self, key, val);
+        }
+        fn visit_val(&mut self, node: &'ast Val) {
+            if let Val::Int(i) = node {
+                self.strs.push(i.to_string());
+            }
+            visit_val(self, node);
+        }
+    }
+
+    #[test]
+    fn walks_every_level() {
+        let input: syn::DeriveInput = syn::parse_quote! {
+            #[fsm(name = "fsm", unit = "s")]
+            enum Fsm {
+                #[fsm(trans(to = "B"), trans(to = "C", when(event = "go")))]
+                A { #[fsm(stock(val = "1"))] a: f64 },
+                B(#[fsm(steps = "[1, [2]]")] f64),
+            }
+        };
+        let opts = Options {
+            embedded: true,
+            inherit: vec![Symbol("unit")],
+            ..Options::default()
+        };
+        let cx = Ctxt::new();
+        let cont = from_ast_with(&cx, &input, Symbol("fsm"), &opts).unwrap();
+        cx.check().unwrap();
+
+        let mut collect = Collect::default();
+        collect.visit_container(&cont);
+        collect.strs.sort();
+        // `unit` once, the inherited copies are not visited.
+        assert_eq!(collect.strs, ["1", "2", "event=go", "name=fsm", "to=B", "to=C", "unit=s", "val=1"]);
+        assert_eq!(collect.fields, ["a", "0"]);
+    }
+}
@@ -0,0 +1,150 @@
+//! In-place traversal of a parsed [`Container`], modeled on `syn::visit_mut`.
+//!
+//! Keys can't change in place, replace the whole map in [`VisitMut::visit_attrs_mut`] or use
+//! [`crate::fold`] for that. The `inherited` vals of variants and fields go through
+//! [`VisitMut::visit_attr_mut`] too, so they change as the container and variant vals they copy.
+//! `cfgs` and `entries` are not visited.
+use std::collections::HashMap;
+
+use crate::internals::ast::{Container, Data, Field, Inherited, Val, Variant};
+
+pub trait VisitMut {
+    fn visit_container_mut(&mut self, node: &mut Container<'_>) {
+        visit_container_mut(self, node)
+    }
+    fn visit_data_mut(&mut self, node: &mut Data<'_>) {
+        visit_data_mut(self, node)
+    }
+    fn visit_variant_mut(&mut self, node: &mut Variant<'_>) {
+        visit_variant_mut(self, node)
+    }
+    fn visit_field_mut(&mut self, node: &mut Field<'_>) {
+        visit_field_mut(self, node)
+    }
+    fn visit_attrs_mut(&mut self, node: &mut HashMap<String, Val>) {
+        visit_attrs_mut(self, node)
+    }
+    fn visit_attr_mut(&mut self, key: &str, val: &mut Val) {
+        visit_attr_mut(self, key, val)
+    }
+    fn visit_val_mut(&mut self, node: &mut Val) {
+        visit_val_mut(self, node)
+    }
+}
+
+pub fn visit_container_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Container<'_>) {
+    v.visit_attrs_mut(&mut node.attrs);
+    v.visit_data_mut(&mut node.data);
+}
+
+pub fn visit_data_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Data<'_>) {
+    match node {
+        Data::Enum(variants) => {
+            for variant in variants {
+                v.visit_variant_mut(variant);
+            }
+        }
+        Data::Struct(_, fields) => {
+            for field in fields {
+                v.visit_field_mut(field);
+            }
+        }
+    }
+}
+
+pub fn visit_variant_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Variant<'_>) {
+    v.visit_attrs_mut(&mut node.attrs);
+    visit_inherited_mut(v, &mut node.inherited);
+    for field in &mut node.fields {
+        v.visit_field_mut(field);
+    }
+}
+
+pub fn visit_field_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Field<'_>) {
+    v.visit_attrs_mut(&mut node.attrs);
+    visit_inherited_mut(v, &mut node.inherited);
+}
+
+fn visit_inherited_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut HashMap<String, Inherited>) {
+    for (key, inherited) in node.iter_mut() {
+        v.visit_attr_mut(key, &mut inherited.val);
+    }
+}
+
+pub fn visit_attrs_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut HashMap<String, Val>) {
+    for (key, val) in node.iter_mut() {
+        v.visit_attr_mut(key, val);
+    }
+}
+
+pub fn visit_attr_mut<V: VisitMut + ?Sized>(v: &mut V, _key: &str, val: &mut Val) {
+    v.visit_val_mut(val);
+}
+
+pub fn visit_val_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Val) {
+    match node {
+        Val::Empty | Val::Str(_) | Val::Bool(_) | Val::Int(_) | Val::Float(_) => {}
+        Val::Map(map) => v.visit_attrs_mut(map),
+        Val::Vec(vs) => {
+            for val in vs {
+                v.visit_val_mut(val);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::internals::ast::Symbol;
+    use crate::internals::ctxt::Ctxt;
+    use crate::internals::parse::{from_ast_with, Options};
+
+    use super::*;
+
+    struct Upper;
+
+    impl VisitMut for Upper {
+        fn visit_val_mut(&mut self, node: &mut Val) {
+            if let Val::Str(s) = node {
+                *s = s.to_uppercase();
+            }
+            visit_val_mut(self, node);
+        }
+    }
+
+    fn str_of(val: &Val) -> &str {
+        match val {
+            Val::Str(s) => s,
+            _ => panic!("expect Val::Str"),
+        }
+    }
+
+    #[test]
+    fn changes_vals_and_inherited_copies() {
+        let input: syn::DeriveInput = syn::parse_quote! {
+            #[sim(unit = "m", algos(names = "a"))]
+            enum Bass {
+                #[sim(unit = "s")]
+                A { #[sim(stock(val = "x"))] a: f64 },
+                B { b: f64 },
+            }
+        };
+        let opts = Options {
+            inherit: vec![Symbol("unit")],
+            ..Options::default()
+        };
+        let cx = Ctxt::new();
+        let mut cont = from_ast_with(&cx, &input, Symbol("sim"), &opts).unwrap();
+        cx.check().unwrap();
+
+        Upper.visit_container_mut(&mut cont);
+        assert_eq!(str_of(cont.attr("algos.names").unwrap()), "A");
+        assert_eq!(str_of(cont.field_attr("a", "stock.val").unwrap()), "X");
+        assert_eq!(str_of(&cont.field("a").unwrap().inherited["unit"].val), "S");
+        assert_eq!(str_of(&cont.field("b").unwrap().inherited["unit"].val), "M");
+        let Data::Enum(variants) = &cont.data else { panic!("expect Data::Enum") };
+        assert_eq!(str_of(&variants[0].inherited["unit"].val), "M");
+        // the entries keep the source.
+        assert_eq!(str_of(&cont.entries[0].val), "m");
+    }
+}
@@ -3,22 +3,30 @@
 //! * "Copy from Copier of Giants". The recommendation is to copy the code into your project for better control.<https://github.com/nealmi/derive-attr-parse>
 //!
 //! ### Typical usage in  proc macro
-//! ```
+//! ```no_run
+//! # extern crate proc_macro;
+//! # use syn::{parse_macro_input, DeriveInput};
+//! # fn demo(inputTokenStream: proc_macro::TokenStream) -> proc_macro::TokenStream {
 //! let input = parse_macro_input!(inputTokenStream as DeriveInput);
 //! let ctx = derive_attr_parser::Ctxt::new();
 //! const DEMO: derive_attr_parser::Symbol = derive_attr_parser::Symbol("demo");
-//! let container = derive_attr_parser::from_ast(&ctx, input, DEMO);
+//! let container = derive_attr_parser::from_ast(&ctx, &input, DEMO);
+//! # ctx.check().unwrap();
+//! # proc_macro::TokenStream::new()
+//! # }
 //! ```
 //!### Full Demo Derive Code
 //!
-//! ```rust
+//! ```no_run
 //! extern crate proc_macro;
 //!
 //! use quote::quote;
 //! use syn::{parse_macro_input, DeriveInput};
 //! use derive_attr_parser::{Ctxt, from_ast, Symbol};
 //!
+//! # /*
 //! #[proc_macro_derive(DemoDerive, attributes(demo))]
+//! # */
 //! pub fn simuples(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 //!     let mut input = parse_macro_input!(input as DeriveInput);
 //!     demo_expand(&mut input)
@@ -39,52 +47,61 @@
 //! ```
 //! Look into [`Container`], [`Field`], [`Val`]
 //! ### Usage of Demo Derive
-//! ```rust
-//!#[derive(DemoDerive)]
-//! #[demo(
-//! name = "test",
-//! method = "system_dynamics",
-//! ode_solver = "eula",
-//! input_name = "BassInput",
-//! output_name = "BassOutput"
-//! )]
-//!#[demo(input(name = "BassInput", ty = "struct"))]
-//!#[demo(output(name = "BassOutput", ty = "struct"))]
-//! pub struct Bass {
-//!     #[demo(param(val = "10_000_f64"), input(from = "total_population"))]
-//!     total_population: f64,
-//!     #[demo(param(val = "0.015_f64"))]
-//!     ad_effectiveness: f64,
-//!     #[demo(param(val = "100_f64"))]
-//!     contact_rate: f64,
-//!     #[demo(param(val = "0.011_f64"))]
-//!     sales_fraction: f64,
-//!     #[demo(var(val = "potential_clients * ad_effectiveness"))]
-//!     sales_from_ad: f64,
-//!     #[demo(var(
-//!     val = "clients * contact_rate * sales_fraction * potential_clients / total_population"
-//!     ))]
-//!     sales_from_wom: f64,
-//!
-//!     #[demo(stock(val = "total_population"), output(to = "potential_clients"))]
-//!     potential_clients: f64,
-//!     #[demo(stock, output(to = "clients"))]
-//!     clients: f64,
+//! The expansion implements `derive_attr_runtime::AttrReflect`, add the runtime next to `demo-derive`,
+//! or point `#[demo(crate = "my_crate::runtime")]` at a re-export of it:
+//! ```toml
+//! [dependencies]
+//! demo-derive = { path = "../demo-derive" }
+//! derive-attr-runtime = { path = "../derive-attr-runtime" }
+//! ```
+//! A model as `#[derive(DemoDerive)]` receives it, and what [`from_ast`] makes of it:
+//! ```
+//! use derive_attr_parser::{from_ast, Ctxt, Symbol, Val};
 //!
+//! let input: syn::DeriveInput = syn::parse_quote! {
 //!     #[demo(
-//!     flow(
-//!     from = "potential_clients",
-//!     to = "clients",
-//!     val = "sales_from_ad + sales_from_wom"
-//!     ),
-//!     output(to = "sales")
+//!     name = "test",
+//!     method = "system_dynamics",
+//!     ode_solver = "eula",
+//!     input_name = "BassInput",
+//!     output_name = "BassOutput"
 //!     )]
-//!     sales: f64,
-//!}
+//!     pub struct Bass {
+//!         #[demo(param(val = "10_000_f64"), input(from = "total_population"))]
+//!         total_population: f64,
+//!         #[demo(var(val = "potential_clients * 0.015"))]
+//!         sales_from_ad: f64,
+//!         #[demo(stock(val = "total_population"), output(to = "potential_clients"))]
+//!         potential_clients: f64,
+//!         #[demo(stock, output(to = "clients"))]
+//!         clients: f64,
+//!         #[demo(
+//!         flow(from = "potential_clients", to = "clients", val = "sales_from_ad"),
+//!         output(to = "sales")
+//!         )]
+//!         sales: f64,
+//!     }
+//! };
+//! let ctx = Ctxt::new();
+//! let cont = from_ast(&ctx, &input, Symbol("demo"));
+//! ctx.check().unwrap();
+//! let cont = cont.unwrap();
+//! assert!(matches!(&cont.attrs["method"], Val::Str(method) if method == "system_dynamics"));
+//! let sales = cont.field("sales").unwrap();
+//! let Val::Map(flow) = &sales.attrs["flow"] else { panic!() };
+//! assert!(matches!(&flow["to"], Val::Str(to) if to == "clients"));
+//! assert!(matches!(cont.field("clients").unwrap().attrs["stock"], Val::Empty));
 //! ```
 //!
 mod internals;
 
+pub use internals::{fold, visit, visit_mut};
+
 pub use internals::ast::*;
 pub use internals::ctxt::Ctxt;
-pub use internals::parse::from_ast;
+pub use internals::foreign::{parse_derives, parse_foreign, parse_repr, Repr};
+pub use internals::literal::parse_embedded;
+pub use internals::query::get_path;
+pub use internals::reflect::reflect_assert;
+pub use internals::ty::CollectionKind;
+pub use internals::parse::{from_ast, from_ast_with, MergePolicy, Options};
@@ -0,0 +1,333 @@
+//! # Runtime reflection of derive attributes
+//! The counterpart of `Container::reflect_tokens` in `derive-attr-parser`: a derive puts the
+//! parsed attributes into a `const`, the running program reads them back through [`AttrReflect`].
+//!
+//! ```
+//! use derive_attr_runtime::{AttrReflect, ContainerMeta, FieldMeta, StaticVal};
+//!
+//! struct Bass;
+//! // What `#[derive(DemoDerive)]` generates for
+//! // struct Bass { #[demo(stock(val = "total_population"))] clients: f64 }
+//! impl AttrReflect for Bass {
+//!     const META: ContainerMeta = ContainerMeta {
+//!         ident: "Bass",
+//!         attrs: &[],
+//!         fields: &[FieldMeta {
+//!             name: "clients",
+//!             ty: "f64",
+//!             attrs: &[("stock", StaticVal::Map(&[("val", StaticVal::Str("total_population"))]))],
+//!         }],
+//!         variants: &[],
+//!     };
+//! }
+//!
+//! let stocks: Vec<_> = Bass::META.fields_with("stock").map(|f| f.name).collect();
+//! assert_eq!(stocks, ["clients"]);
+//! assert_eq!(Bass::META.field("clients").unwrap().attr("stock.val").unwrap().as_str(), Some("total_population"));
+//! ```
+//!
+//! The const fns work at compile time too, a derive on a composite type checks the types it
+//! refers to with `reflect_assert` of `derive-attr-parser`:
+//! ```
+//! # use derive_attr_runtime::{AttrReflect, ContainerMeta};
+//! # struct Bass;
+//! # impl AttrReflect for Bass {
+//! #     const META: ContainerMeta = ContainerMeta { ident: "Bass", attrs: &[], fields: &[], variants: &[] };
+//! # }
+//! const _: () = assert!(!Bass::META.has_field("sales"), "`Bass` has no field `sales`");
+//! ```
+
+/// Attributes as `(key, val)` pairs, sorted by key.
+pub type Attrs = &'static [(&'static str, StaticVal)];
+
+/// Implemented by the derives, see `Container::reflect_tokens`.
+pub trait AttrReflect {
+    const META: ContainerMeta;
+}
+
+/// The `'static` counterpart of `Val`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StaticVal {
+    Empty,
+    Str(&'static str),
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Map(Attrs),
+    Vec(&'static [StaticVal]),
+}
+
+/// The struct or enum.
+#[derive(Debug, Clone, Copy)]
+pub struct ContainerMeta {
+    pub ident: &'static str,
+    pub attrs: Attrs,
+    /// The fields of a struct, empty for an enum.
+    pub fields: &'static [FieldMeta],
+    /// The variants of an enum, empty for a struct.
+    pub variants: &'static [VariantMeta],
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct VariantMeta {
+    pub ident: &'static str,
+    pub attrs: Attrs,
+    pub fields: &'static [FieldMeta],
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FieldMeta {
+    /// The field ident, or the index of a tuple field.
+    pub name: &'static str,
+    /// The type as written, eg. `HashMap < u64 , Person >`.
+    pub ty: &'static str,
+    pub attrs: Attrs,
+}
+
+/// `==` of str, which is not const yet.
+pub const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// The val of `key` in `attrs`.
+pub const fn get(attrs: Attrs, key: &str) -> Option<&'static StaticVal> {
+    let mut i = 0;
+    while i < attrs.len() {
+        if str_eq(attrs[i].0, key) {
+            return Some(&attrs[i].1);
+        }
+        i += 1;
+    }
+    None
+}
+
+/// The val of the dotted `path` in `attrs`, like `get_path` of derive-attr-parser.
+pub fn get_path(attrs: Attrs, path: &str) -> Option<&'static StaticVal> {
+    let mut segs = path.split('.');
+    let mut val = get(attrs, segs.next()?)?;
+    for seg in segs {
+        val = match val {
+            StaticVal::Map(map) => get(map, seg)?,
+            StaticVal::Vec(vs) => vs.get(seg.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(val)
+}
+
+/// Whether the val at `path`, or any item of a `StaticVal::Vec` on the way, is `Str(value)`.
+///
+/// `has_str(field.attrs, &["output", "to"], "total")` for `#[demo(output(to = "total"))]`.
+pub const fn has_str(attrs: Attrs, path: &[&str], value: &str) -> bool {
+    val_has_str(&StaticVal::Map(attrs), path, 0, value)
+}
+
+const fn val_has_str(val: &StaticVal, path: &[&str], at: usize, value: &str) -> bool {
+    match val {
+        StaticVal::Vec(vs) => {
+            let mut i = 0;
+            while i < vs.len() {
+                if val_has_str(&vs[i], path, at, value) {
+                    return true;
+                }
+                i += 1;
+            }
+            false
+        }
+        StaticVal::Str(s) if at == path.len() => str_eq(s, value),
+        StaticVal::Map(map) if at < path.len() => match get(map, path[at]) {
+            Some(val) => val_has_str(val, path, at + 1, value),
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+impl StaticVal {
+    pub const fn as_str(&self) -> Option<&'static str> {
+        match self {
+            StaticVal::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The val of `key` in a `StaticVal::Map`.
+    pub const fn get(&self, key: &str) -> Option<&'static StaticVal> {
+        match self {
+            StaticVal::Map(map) => get(map, key),
+            _ => None,
+        }
+    }
+}
+
+impl ContainerMeta {
+    /// The field named `name` of a struct.
+    pub const fn field(&self, name: &str) -> Option<&'static FieldMeta> {
+        find_field(self.fields, name)
+    }
+
+    pub const fn has_field(&self, name: &str) -> bool {
+        self.field(name).is_some()
+    }
+
+    /// See [`has_str`].
+    pub const fn attr_has_str(&self, path: &[&str], value: &str) -> bool {
+        has_str(self.attrs, path, value)
+    }
+
+    /// [`has_str`] over the fields of a struct.
+    pub const fn any_field_has_str(&self, path: &[&str], value: &str) -> bool {
+        let mut i = 0;
+        while i < self.fields.len() {
+            if has_str(self.fields[i].attrs, path, value) {
+                return true;
+            }
+            i += 1;
+        }
+        false
+    }
+
+    pub const fn variant(&self, ident: &str) -> Option<&'static VariantMeta> {
+        let mut i = 0;
+        while i < self.variants.len() {
+            if str_eq(self.variants[i].ident, ident) {
+                return Some(&self.variants[i]);
+            }
+            i += 1;
+        }
+        None
+    }
+
+    pub fn attr(&self, path: &str) -> Option<&'static StaticVal> {
+        get_path(self.attrs, path)
+    }
+
+    /// The fields of the struct, or of all variants of the enum.
+    pub fn all_fields(&self) -> impl Iterator<Item = &'static FieldMeta> {
+        let fields: &'static [FieldMeta] = self.fields;
+        let variants: &'static [VariantMeta] = self.variants;
+        fields
+            .iter()
+            .chain(variants.iter().flat_map(|variant| variant.fields.iter()))
+    }
+
+    /// The fields having the top level `key`, eg. `Bass::META.fields_with("stock")`.
+    pub fn fields_with<'k>(&self, key: &'k str) -> impl Iterator<Item = &'static FieldMeta> + 'k {
+        self.all_fields()
+            .filter(move |field| get(field.attrs, key).is_some())
+    }
+}
+
+impl VariantMeta {
+    pub fn attr(&self, path: &str) -> Option<&'static StaticVal> {
+        get_path(self.attrs, path)
+    }
+}
+
+impl FieldMeta {
+    pub fn attr(&self, path: &str) -> Option<&'static StaticVal> {
+        get_path(self.attrs, path)
+    }
+}
+
+const fn find_field(fields: &'static [FieldMeta], name: &str) -> Option<&'static FieldMeta> {
+    let mut i = 0;
+    while i < fields.len() {
+        if str_eq(fields[i].name, name) {
+            return Some(&fields[i]);
+        }
+        i += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // struct Composite { #[demo(output(to = "total"), output(to = "rate"))] a: f64, #[demo(stock)] b: f64 }
+    // enum Fsm { #[demo(trans(to = "B"))] A { #[demo(stock)] c: f64 } }
+    const COMPOSITE: ContainerMeta = ContainerMeta {
+        ident: "Composite",
+        attrs: &[("algos", StaticVal::Map(&[("k", StaticVal::Int(8))])), ("name", StaticVal::Str("c"))],
+        fields: &[
+            FieldMeta {
+                name: "a",
+                ty: "f64",
+                attrs: &[(
+                    "output",
+                    StaticVal::Vec(&[
+                        StaticVal::Map(&[("to", StaticVal::Str("total"))]),
+                        StaticVal::Map(&[("to", StaticVal::Str("rate"))]),
+                    ]),
+                )],
+            },
+            FieldMeta { name: "b", ty: "f64", attrs: &[("stock", StaticVal::Empty)] },
+        ],
+        variants: &[],
+    };
+    const FSM: ContainerMeta = ContainerMeta {
+        ident: "Fsm",
+        attrs: &[],
+        fields: &[],
+        variants: &[VariantMeta {
+            ident: "A",
+            attrs: &[("trans", StaticVal::Map(&[("to", StaticVal::Str("B"))]))],
+            fields: &[FieldMeta { name: "c", ty: "f64", attrs: &[("stock", StaticVal::Empty)] }],
+        }],
+    };
+
+    // evaluated by the compiler, a false one fails the build.
+    const _: () = {
+        assert!(str_eq("total", "total") && !str_eq("total", "tota") && !str_eq("a", "b"));
+        assert!(COMPOSITE.has_field("b") && !COMPOSITE.has_field("c"));
+        assert!(COMPOSITE.any_field_has_str(&["output", "to"], "rate"));
+        assert!(!COMPOSITE.any_field_has_str(&["output", "to"], "sales"));
+        assert!(COMPOSITE.attr_has_str(&["name"], "c"));
+        assert!(!COMPOSITE.attr_has_str(&["name", "to"], "c"));
+        assert!(FSM.variant("A").is_some() && FSM.variant("B").is_none());
+    };
+
+    #[test]
+    fn const_lookups() {
+        assert!(has_str(COMPOSITE.fields[0].attrs, &["output", "to"], "total"));
+        // the path ends at a map, no str there.
+        assert!(!has_str(COMPOSITE.fields[0].attrs, &["output"], "total"));
+        assert_eq!(get(COMPOSITE.attrs, "name").and_then(StaticVal::as_str), Some("c"));
+        assert_eq!(get(COMPOSITE.attrs, "nam"), None);
+        let algos = get(COMPOSITE.attrs, "algos").unwrap();
+        assert_eq!(algos.get("k"), Some(&StaticVal::Int(8)));
+        assert_eq!(StaticVal::Int(8).get("k"), None);
+    }
+
+    #[test]
+    fn paths_and_fields() {
+        assert_eq!(COMPOSITE.attr("algos.k"), Some(&StaticVal::Int(8)));
+        assert_eq!(COMPOSITE.attr("algos.j"), None);
+        let a = COMPOSITE.field("a").unwrap();
+        assert_eq!(a.attr("output.1.to").and_then(StaticVal::as_str), Some("rate"));
+        assert_eq!(a.attr("output.2.to"), None);
+        assert_eq!(a.attr("output.x"), None);
+        assert_eq!(a.attr("output.0.to.x"), None);
+
+        let names: Vec<_> = FSM.all_fields().map(|field| field.name).collect();
+        assert_eq!(names, ["c"]);
+        let stocks: Vec<_> = FSM.fields_with("stock").map(|field| field.name).collect();
+        assert_eq!(stocks, ["c"]);
+        // `field` only looks at the struct fields.
+        assert!(FSM.field("c").is_none());
+        let trans = FSM.variant("A").unwrap().attr("trans.to");
+        assert_eq!(trans.and_then(StaticVal::as_str), Some("B"));
+    }
+}